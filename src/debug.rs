@@ -2,7 +2,7 @@ use bevy::{input::common_conditions::input_toggle_active, prelude::*};
 use bevy_infinite_grid::{InfiniteGridBundle, InfiniteGridPlugin};
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
-use crate::GameState;
+use crate::{game::OnGameScreen, GameState};
 
 pub struct DebugPlugin;
 
@@ -17,5 +17,5 @@ impl Plugin for DebugPlugin {
 }
 
 fn spawn_grid(mut commands: Commands) {
-    commands.spawn(InfiniteGridBundle::default());
+    commands.spawn((InfiniteGridBundle::default(), OnGameScreen));
 }
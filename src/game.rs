@@ -1,12 +1,17 @@
 mod camera;
 mod economy;
+mod footprint;
+mod grid;
+mod hud;
+mod indicators;
+mod map;
 mod placement;
+mod projectile;
 mod roll;
+mod status;
 mod wave;
 
-use super::GameState;
-use bevy::gltf::GltfMesh;
-use bevy::math::vec2;
+use super::{despawn_screen, GameState};
 use bevy::prelude::*;
 use bevy::utils::HashMap;
 use bevy::{ecs::system::SystemState, gltf::Gltf, render::primitives::Aabb};
@@ -14,14 +19,24 @@ use bevy_asset_loader::prelude::*;
 use bevy_common_assets::ron::RonAssetPlugin;
 use camera::CameraPlugin;
 use economy::EconomyPlugin;
+use footprint::Hexomino;
+use grid::GridPlugin;
+use hud::HudPlugin;
+use indicators::IndicatorsPlugin;
+use map::MapPlugin;
 use placement::PlacementPlugin;
-use rand::seq::IteratorRandom;
+use projectile::ProjectilePlugin;
+use rand::seq::{IteratorRandom, SliceRandom};
 use rand::Rng;
 use roll::RollPlugin;
+use status::{StatusEffect, StatusPlugin};
 use std::f32::consts::PI;
 use std::time::Duration;
 use vleue_navigator::prelude::*;
-use wave::{EnemySpawner, WavePlugin};
+use wave::{EnemyAbility, WavePlugin};
+
+pub use map::{MapAssets, MapDetails, SelectedMap};
+pub use wave::WaveCounter;
 
 const SNAP_OFFSET: f32 = 0.5;
 
@@ -29,12 +44,18 @@ pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.init_state::<GamePlayState>()
+        app.add_sub_state::<GamePlayState>()
             .add_plugins((
                 CameraPlugin,
                 EconomyPlugin,
+                GridPlugin,
+                HudPlugin,
+                IndicatorsPlugin,
+                MapPlugin,
                 PlacementPlugin,
+                ProjectilePlugin,
                 RollPlugin,
+                StatusPlugin,
                 WavePlugin,
                 RonAssetPlugin::<AssetCollections>::new(&["game.ron"]),
                 VleueNavigatorPlugin,
@@ -42,16 +63,13 @@ impl Plugin for GamePlugin {
             ))
             .init_resource::<Assets<TowerDetails>>()
             .init_resource::<Assets<EnemyDetails>>()
+            .init_resource::<Assets<WaveDetails>>()
+            .init_resource::<Assets<ElementChart>>()
+            .init_resource::<Assets<FaceWeights>>()
+            .init_resource::<Assets<ShopDetails>>()
+            .init_resource::<Assets<EconomyDetails>>()
             .init_resource::<DiePool>()
             .init_resource::<TowerPool>()
-            .insert_resource(DiePool {
-                dice: Vec::new(),
-                highlighted: 0,
-            })
-            .insert_resource(TowerPool {
-                towers: Vec::new(),
-                highlighted: 0,
-            })
             .register_type::<DiePool>()
             .add_event::<DiePurchaseEvent>()
             .add_event::<DieRolledEvent>()
@@ -59,12 +77,14 @@ impl Plugin for GamePlugin {
             .add_systems(
                 Update,
                 (die_purchased, die_rolled).run_if(in_state(GameState::Game)),
-            );
+            )
+            .add_systems(OnExit(GameState::Game), despawn_screen::<OnGameScreen>);
     }
 }
 
-// Enum that will be used as a state for the gameplay loop
-#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
+// Enum that will be used as a state for the gameplay loop, only exists while in game
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, SubStates)]
+#[source(GameState = GameState::Game)]
 enum GamePlayState {
     #[default]
     Economy,
@@ -76,12 +96,26 @@ enum GamePlayState {
 #[derive(Component, Debug)]
 struct Obstacle;
 
+// Tag component used to tag entities that only live for the length of a game
+#[derive(Component)]
+pub struct OnGameScreen;
+
 #[derive(AssetCollection, Resource)]
 pub struct AllAssets {
     #[asset(key = "towers", collection(typed))]
     pub towers: Vec<Handle<TowerDetails>>,
     #[asset(key = "enemies", collection(typed))]
     pub enemies: Vec<Handle<EnemyDetails>>,
+    #[asset(key = "waves", collection(typed))]
+    pub waves: Vec<Handle<WaveDetails>>,
+    #[asset(key = "elements")]
+    pub elements: Handle<ElementChart>,
+    #[asset(key = "face_weights")]
+    pub face_weights: Handle<FaceWeights>,
+    #[asset(key = "shop")]
+    pub shop: Handle<ShopDetails>,
+    #[asset(key = "economy")]
+    pub economy: Handle<EconomyDetails>,
 }
 
 /// Representation of a loaded tower file.
@@ -90,6 +124,29 @@ pub struct TowerDetails {
     pub name: String,
     pub element_type: BaseElementType,
     pub model: Handle<Gltf>,
+    pub range: f32,
+    pub damage: u32,
+    pub attack_interval: f32,
+    pub projectile_speed: f32,
+    pub projectile_model: Option<Handle<Gltf>>,
+    pub projectile: ProjectileKind,
+    pub projectile_color: Color,
+    pub projectile_size: f32,
+    pub targeting: TargetingMode,
+    pub effects: Vec<StatusEffect>,
+    pub can_target_ground: bool,
+    pub can_target_air: bool,
+    pub upgrades: Vec<TowerUpgrade>,
+}
+
+/// One tier a placed tower can be upgraded to, bought in order.
+#[derive(Debug, Clone)]
+pub struct TowerUpgrade {
+    pub cost: usize,
+    pub damage: u32,
+    pub range: f32,
+    pub attack_interval: Option<f32>,
+    pub model: Option<Handle<Gltf>>,
 }
 
 /// Representation of a loaded enemy file.
@@ -98,13 +155,99 @@ pub struct EnemyDetails {
     pub name: String,
     pub health: u32,
     pub speed: f32,
+    pub damage: u32,
+    pub element_type: BaseElementType,
+    pub movement: MovementType,
+    pub abilities: Vec<EnemyAbility>,
     pub model: Handle<Gltf>,
+    pub phases: Vec<EnemyPhase>,
+    pub bounty: usize,
+}
+
+/// Changes an enemy goes through once its health drops low enough.
+#[derive(Debug, Clone)]
+pub struct EnemyPhase {
+    pub health_fraction: f32,
+    pub speed: Option<f32>,
+    pub abilities: Option<Vec<EnemyAbility>>,
+    pub model: Option<Handle<Gltf>>,
+}
+
+/// Representation of a loaded wave file.
+#[derive(Asset, Debug, TypePath)]
+pub struct WaveDetails {
+    pub groups: Vec<WaveGroup>,
+    pub boss: Option<WaveBoss>,
+    pub reward: usize,
+}
+
+/// A group of identical enemies spawned at a regular interval during a wave.
+#[derive(Debug, Clone)]
+pub struct WaveGroup {
+    pub enemy: String,
+    pub count: u32,
+    pub interval: Duration,
+    pub delay: Duration,
+    pub spawner: usize,
+}
+
+/// A single enemy with its own health bar, spawned once during a wave.
+#[derive(Debug, Clone)]
+pub struct WaveBoss {
+    pub enemy: String,
+    pub delay: Duration,
+    pub spawner: usize,
+    pub bounty: usize,
+}
+
+/// How effective each element is against the others, loaded from `game.ron`.
+#[derive(Asset, Debug, TypePath)]
+pub struct ElementChart {
+    pub matchups: Vec<ElementMatchupRon>,
+}
+
+impl ElementChart {
+    /// Damage multiplier for an attack of one element against an enemy of another,
+    /// elements without a matchup deal normal damage.
+    pub fn multiplier(&self, attacker: &BaseElementType, defender: &BaseElementType) -> f32 {
+        self.matchups
+            .iter()
+            .find(|matchup| &matchup.attacker == attacker && &matchup.defender == defender)
+            .map(|matchup| matchup.multiplier)
+            .unwrap_or(1.0)
+    }
+}
+
+/// What the die shop sells and how it restocks, loaded from `game.ron`.
+#[derive(Asset, Debug, TypePath)]
+pub struct ShopDetails {
+    pub offers: usize,
+    pub reroll_cost: usize,
+    pub reroll_cost_increase: usize,
+    pub sale_chance: f64,
+    pub sale_percent: usize,
+    pub items: Vec<ShopItemRon>,
+}
+
+/// Where money comes from besides kills, loaded from `game.ron`.
+#[derive(Asset, Debug, TypePath)]
+pub struct EconomyDetails {
+    pub starting_money: usize,
+    pub wave_clear_bonus: usize,
+    pub interest_percent: usize,
+    pub interest_cap: usize,
+    pub perfect_wave_bonus: usize,
 }
 
 #[derive(serde::Deserialize, Debug, Clone)]
 enum CustomDynamicAsset {
     Towers(Vec<TowerDetailsRon>),
     Enemies(Vec<EnemyDetailsRon>),
+    Waves(Vec<WaveDetailsRon>),
+    Elements(Vec<ElementMatchupRon>),
+    FaceWeights(FaceWeightsRon),
+    Shop(ShopRon),
+    Economy(EconomyRon),
 }
 
 impl DynamicAsset for CustomDynamicAsset {
@@ -112,12 +255,26 @@ impl DynamicAsset for CustomDynamicAsset {
         match self {
             CustomDynamicAsset::Towers(towers) => towers
                 .iter()
-                .map(|tower| asset_server.load::<Gltf>(tower.model.clone()).untyped())
+                .flat_map(|tower| {
+                    std::iter::once(&tower.model)
+                        .chain(&tower.projectile_model)
+                        .chain(tower.upgrades.iter().flat_map(|upgrade| &upgrade.model))
+                })
+                .map(|model| asset_server.load::<Gltf>(model.clone()).untyped())
                 .collect(),
             CustomDynamicAsset::Enemies(enemies) => enemies
                 .iter()
-                .map(|enemy| asset_server.load::<Gltf>(enemy.model.clone()).untyped())
+                .flat_map(|enemy| {
+                    std::iter::once(&enemy.model)
+                        .chain(enemy.phases.iter().flat_map(|phase| &phase.model))
+                })
+                .map(|model| asset_server.load::<Gltf>(model.clone()).untyped())
                 .collect(),
+            CustomDynamicAsset::Waves(_)
+            | CustomDynamicAsset::Elements(_)
+            | CustomDynamicAsset::Shop(_)
+            | CustomDynamicAsset::Economy(_)
+            | CustomDynamicAsset::FaceWeights(_) => vec![],
         }
     }
 
@@ -126,16 +283,49 @@ impl DynamicAsset for CustomDynamicAsset {
             CustomDynamicAsset::Towers(towers) => {
                 let mut towers_collection = vec![];
                 for tower in towers {
-                    let model = world
-                        .get_resource::<AssetServer>()
-                        .unwrap()
-                        .load(tower.model.clone());
+                    let asset_server = world.get_resource::<AssetServer>().unwrap();
+                    let model = asset_server.load(tower.model.clone());
+                    let projectile_model = tower
+                        .projectile_model
+                        .as_ref()
+                        .map(|model| asset_server.load(model.clone()));
+                    let upgrades = tower
+                        .upgrades
+                        .iter()
+                        .map(|upgrade| TowerUpgrade {
+                            cost: upgrade.cost,
+                            damage: upgrade.damage,
+                            range: upgrade.range,
+                            attack_interval: upgrade.attack_interval,
+                            model: upgrade
+                                .model
+                                .as_ref()
+                                .map(|model| asset_server.load(model.clone())),
+                        })
+                        .collect();
                     let mut tower_details =
                         SystemState::<ResMut<Assets<TowerDetails>>>::new(world).get_mut(world);
                     let handle = tower_details.add(TowerDetails {
                         name: tower.name.clone(),
                         element_type: tower.element_type.clone(),
                         model: model.clone(),
+                        range: tower.range,
+                        damage: tower.damage,
+                        attack_interval: tower.attack_interval,
+                        projectile_speed: tower.projectile_speed,
+                        projectile_model,
+                        projectile: tower.projectile.clone(),
+                        projectile_color: Color::srgb(
+                            tower.projectile_color.0,
+                            tower.projectile_color.1,
+                            tower.projectile_color.2,
+                        ),
+                        projectile_size: tower.projectile_size,
+                        targeting: tower.targeting,
+                        effects: tower.effects.clone(),
+                        can_target_ground: tower.can_target_ground,
+                        can_target_air: tower.can_target_air,
+                        upgrades,
                     });
                     towers_collection.push(handle.untyped());
                     info!("Built tower: {}", tower.name);
@@ -145,22 +335,111 @@ impl DynamicAsset for CustomDynamicAsset {
             CustomDynamicAsset::Enemies(enemies) => {
                 let mut enemies_collection = vec![];
                 for enemy in enemies {
-                    let model = world
-                        .get_resource::<AssetServer>()
-                        .unwrap()
-                        .load(enemy.model.clone());
+                    let asset_server = world.get_resource::<AssetServer>().unwrap();
+                    let model = asset_server.load(enemy.model.clone());
+                    let phases = enemy
+                        .phases
+                        .iter()
+                        .map(|phase| EnemyPhase {
+                            health_fraction: phase.health_fraction,
+                            speed: phase.speed,
+                            abilities: phase.abilities.clone(),
+                            model: phase
+                                .model
+                                .as_ref()
+                                .map(|model| asset_server.load(model.clone())),
+                        })
+                        .collect();
                     let mut assets = world.get_resource_mut::<Assets<EnemyDetails>>().unwrap();
                     let handle = assets.add(EnemyDetails {
                         name: enemy.name.clone(),
                         health: enemy.health,
                         speed: enemy.speed,
+                        damage: enemy.damage,
+                        element_type: enemy.element_type.clone(),
+                        movement: enemy.movement,
+                        abilities: enemy.abilities.clone(),
                         model: model.clone(),
+                        phases,
+                        bounty: enemy.bounty,
                     });
                     enemies_collection.push(handle.untyped());
                     info!("Built enemy: {}", enemy.name);
                 }
                 Ok(DynamicAssetType::Collection(enemies_collection))
             }
+            CustomDynamicAsset::Waves(waves) => {
+                let mut waves_collection = vec![];
+                for (i, wave) in waves.iter().enumerate() {
+                    let mut assets = world.get_resource_mut::<Assets<WaveDetails>>().unwrap();
+                    let handle = assets.add(WaveDetails {
+                        groups: wave
+                            .groups
+                            .iter()
+                            .map(|group| WaveGroup {
+                                enemy: group.enemy.clone(),
+                                count: group.count,
+                                interval: Duration::from_secs_f32(group.interval),
+                                delay: Duration::from_secs_f32(group.delay),
+                                spawner: group.spawner,
+                            })
+                            .collect(),
+                        boss: wave.boss.as_ref().map(|boss| WaveBoss {
+                            enemy: boss.enemy.clone(),
+                            delay: Duration::from_secs_f32(boss.delay),
+                            spawner: boss.spawner,
+                            bounty: boss.bounty,
+                        }),
+                        reward: wave.reward,
+                    });
+                    waves_collection.push(handle.untyped());
+                    info!("Built wave: {}", i + 1);
+                }
+                Ok(DynamicAssetType::Collection(waves_collection))
+            }
+            CustomDynamicAsset::Elements(matchups) => {
+                let mut assets = world.get_resource_mut::<Assets<ElementChart>>().unwrap();
+                let handle = assets.add(ElementChart {
+                    matchups: matchups.clone(),
+                });
+                info!("Built element chart");
+                Ok(DynamicAssetType::Single(handle.untyped()))
+            }
+            CustomDynamicAsset::FaceWeights(weights) => {
+                let mut assets = world.get_resource_mut::<Assets<FaceWeights>>().unwrap();
+                let handle = assets.add(FaceWeights {
+                    main_element: weights.main_element,
+                    elements: weights.elements.clone(),
+                    rarities: weights.rarities.clone(),
+                });
+                info!("Built face weights");
+                Ok(DynamicAssetType::Single(handle.untyped()))
+            }
+            CustomDynamicAsset::Shop(shop) => {
+                let mut assets = world.get_resource_mut::<Assets<ShopDetails>>().unwrap();
+                let handle = assets.add(ShopDetails {
+                    offers: shop.offers,
+                    reroll_cost: shop.reroll_cost,
+                    reroll_cost_increase: shop.reroll_cost_increase,
+                    sale_chance: shop.sale_chance,
+                    sale_percent: shop.sale_percent,
+                    items: shop.items.clone(),
+                });
+                info!("Built shop with {} items", shop.items.len());
+                Ok(DynamicAssetType::Single(handle.untyped()))
+            }
+            CustomDynamicAsset::Economy(economy) => {
+                let mut assets = world.get_resource_mut::<Assets<EconomyDetails>>().unwrap();
+                let handle = assets.add(EconomyDetails {
+                    starting_money: economy.starting_money,
+                    wave_clear_bonus: economy.wave_clear_bonus,
+                    interest_percent: economy.interest_percent,
+                    interest_cap: economy.interest_cap,
+                    perfect_wave_bonus: economy.perfect_wave_bonus,
+                });
+                info!("Built economy");
+                Ok(DynamicAssetType::Single(handle.untyped()))
+            }
         }
     }
 }
@@ -170,6 +449,70 @@ pub struct TowerDetailsRon {
     pub name: String,
     pub element_type: BaseElementType,
     pub model: String,
+    /// Distance at which the tower starts shooting at enemies
+    pub range: f32,
+    /// Damage dealt by each projectile
+    pub damage: u32,
+    /// Seconds between each shot
+    pub attack_interval: f32,
+    pub projectile_speed: f32,
+    /// Model used for the projectile, a plain sphere if not set
+    #[serde(default)]
+    pub projectile_model: Option<String>,
+    /// How the tower's attacks reach enemies
+    #[serde(default)]
+    pub projectile: ProjectileKind,
+    /// Colour of the tower's projectiles, beams and splashes, as red, green and blue
+    #[serde(default = "default_projectile_color")]
+    pub projectile_color: (f32, f32, f32),
+    /// Radius of the tower's projectiles, and of its beams
+    #[serde(default = "default_projectile_size")]
+    pub projectile_size: f32,
+    /// Which enemy in range the tower shoots at, until changed during placement
+    #[serde(default)]
+    pub targeting: TargetingMode,
+    /// Status effects applied to each enemy hit
+    #[serde(default)]
+    pub effects: Vec<StatusEffect>,
+    /// Whether the tower shoots at ground enemies
+    #[serde(default = "default_true")]
+    pub can_target_ground: bool,
+    /// Whether the tower shoots at flying enemies
+    #[serde(default = "default_true")]
+    pub can_target_air: bool,
+    /// Tiers the tower can be upgraded through once placed, in order
+    #[serde(default)]
+    pub upgrades: Vec<TowerUpgradeRon>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct TowerUpgradeRon {
+    /// Money the upgrade costs
+    pub cost: usize,
+    /// Damage added to each attack
+    #[serde(default)]
+    pub damage: u32,
+    /// Distance added to the tower's range
+    #[serde(default)]
+    pub range: f32,
+    /// Seconds between each shot, unchanged if not set
+    #[serde(default)]
+    pub attack_interval: Option<f32>,
+    /// Model the tower changes to, unchanged if not set
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_projectile_color() -> (f32, f32, f32) {
+    (1.0, 0.0, 0.0)
+}
+
+fn default_projectile_size() -> f32 {
+    0.1
 }
 
 #[derive(serde::Deserialize, Asset, Debug, TypePath, Clone)]
@@ -177,13 +520,162 @@ pub struct EnemyDetailsRon {
     pub name: String,
     pub health: u32,
     pub speed: f32,
+    /// Lives lost when the enemy reaches the goal
+    pub damage: u32,
+    #[serde(default)]
+    pub element_type: BaseElementType,
+    #[serde(default)]
+    pub movement: MovementType,
+    #[serde(default)]
+    pub abilities: Vec<EnemyAbility>,
     pub model: String,
+    /// Changes the enemy goes through as it's hurt, in order of decreasing health
+    #[serde(default)]
+    pub phases: Vec<EnemyPhaseRon>,
+    /// Money paid for killing the enemy
+    #[serde(default = "default_bounty")]
+    pub bounty: usize,
 }
 
-#[derive(AssetCollection, Resource)]
-pub struct GltfAssets {
-    #[asset(path = "models/house.glb")]
-    pub house: Handle<Gltf>,
+fn default_bounty() -> usize {
+    10
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct EnemyPhaseRon {
+    /// Fraction of its health the enemy must drop to for the phase to start
+    pub health_fraction: f32,
+    /// New speed, unchanged if not set
+    #[serde(default)]
+    pub speed: Option<f32>,
+    /// Abilities replacing the enemy's current ones, unchanged if not set
+    #[serde(default)]
+    pub abilities: Option<Vec<EnemyAbility>>,
+    /// Model the enemy turns into, unchanged if not set
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(serde::Deserialize, Asset, Debug, TypePath, Clone)]
+pub struct WaveDetailsRon {
+    pub groups: Vec<WaveGroupRon>,
+    /// Boss that comes out once during the wave
+    #[serde(default)]
+    pub boss: Option<WaveBossRon>,
+    /// Money paid out once the wave is cleared
+    #[serde(default)]
+    pub reward: usize,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct WaveGroupRon {
+    /// Name of the enemy, as declared in the enemies collection
+    pub enemy: String,
+    pub count: u32,
+    /// Seconds between each enemy in the group
+    pub interval: f32,
+    /// Seconds to wait after the wave starts before the first enemy
+    #[serde(default)]
+    pub delay: f32,
+    /// Index of the spawner the group comes out of, wrapping around on maps with fewer
+    #[serde(default)]
+    pub spawner: usize,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct WaveBossRon {
+    /// Name of the enemy, as declared in the enemies collection
+    pub enemy: String,
+    /// Seconds to wait after the wave starts before the boss comes out
+    #[serde(default)]
+    pub delay: f32,
+    /// Index of the spawner the boss comes out of, wrapping around on maps with fewer
+    #[serde(default)]
+    pub spawner: usize,
+    /// Money paid out when the boss is killed
+    #[serde(default)]
+    pub bounty: usize,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ElementMatchupRon {
+    pub attacker: BaseElementType,
+    pub defender: BaseElementType,
+    /// Multiplier applied to damage, above 1 for a weakness and below 1 for a resistance
+    pub multiplier: f32,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct FaceWeightsRon {
+    /// Chance of each face being the die's own element, from 0 to 1
+    pub main_element: f64,
+    /// Relative weight of each element the other faces are drawn from
+    pub elements: Vec<(BaseElementType, u32)>,
+    /// Relative weight of each rarity
+    pub rarities: Vec<(Rarity, u32)>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ShopRon {
+    /// Number of dice on offer at once
+    pub offers: usize,
+    /// Cost of the first reroll after each restock
+    pub reroll_cost: usize,
+    /// How much each further reroll before the next restock costs on top
+    #[serde(default)]
+    pub reroll_cost_increase: usize,
+    /// Chance of each offer being on sale, from 0 to 1
+    #[serde(default)]
+    pub sale_chance: f64,
+    /// Percentage knocked off the price of offers on sale
+    #[serde(default)]
+    pub sale_percent: usize,
+    pub items: Vec<ShopItemRon>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct ShopItemRon {
+    /// Element most of the die's faces are
+    pub element: BaseElementType,
+    /// Price before the rarity of its faces is added
+    pub price: usize,
+    /// Dice of this kind that can be bought between restocks before it sells out
+    #[serde(default = "default_stock")]
+    pub stock: usize,
+    /// Waves that have to be cleared before it's offered
+    #[serde(default)]
+    pub unlock_wave: usize,
+    /// Rarity every face is raised to, if it would be any less rare
+    #[serde(default)]
+    pub min_rarity: Rarity,
+    /// How often it's offered, relative to the other items
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct EconomyRon {
+    pub starting_money: usize,
+    /// Paid for every wave cleared, on top of the wave's own reward
+    #[serde(default)]
+    pub wave_clear_bonus: usize,
+    /// Percentage of the money banked at the end of a wave paid as interest
+    #[serde(default)]
+    pub interest_percent: usize,
+    /// Most interest that can be earned in a wave
+    #[serde(default)]
+    pub interest_cap: usize,
+    /// Paid for clearing a wave without any enemy reaching a goal
+    #[serde(default)]
+    pub perfect_wave_bonus: usize,
+}
+
+fn default_stock() -> usize {
+    1
+}
+
+fn default_weight() -> u32 {
+    1
 }
 
 #[derive(serde::Deserialize, Asset, TypePath)]
@@ -198,7 +690,9 @@ impl DynamicAssetCollection for AssetCollections {
 }
 
 #[derive(Default, Component)]
-struct Goal;
+struct Goal {
+    id: usize,
+}
 
 #[derive(Resource, Debug, Clone, PartialEq, Reflect)]
 #[reflect(Resource)]
@@ -209,11 +703,53 @@ struct DieFace {
 
 impl std::fmt::Display for DieFace {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // write the primary type
+        // write the primary type, and the rarity if it's anything special
+        match self.rarity {
+            Rarity::Common => write!(f, "{}", self.primary_type),
+            rarity => write!(f, "{} {}", rarity, self.primary_type),
+        }
+    }
+}
+
+#[derive(Resource, serde::Deserialize, Default, Debug, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Resource)]
+pub enum BaseElementType {
+    #[default]
+    None, // No element
+    Fire,  // Heat and destruction
+    Water, // Flow and adaptability
+    Earth, // Stability and strength
+    Wind,  // Movement and agility
+}
+
+impl BaseElementType {
+    pub const ALL: [BaseElementType; 5] = [
+        BaseElementType::None,
+        BaseElementType::Fire,
+        BaseElementType::Water,
+        BaseElementType::Earth,
+        BaseElementType::Wind,
+    ];
+}
+
+impl BaseElementType {
+    fn color(&self) -> Color {
+        match self {
+            BaseElementType::None => Color::srgb(0.4, 0.4, 0.45),
+            BaseElementType::Fire => Color::srgb(0.8, 0.3, 0.1),
+            BaseElementType::Water => Color::srgb(0.2, 0.4, 0.8),
+            BaseElementType::Earth => Color::srgb(0.4, 0.6, 0.2),
+            BaseElementType::Wind => Color::srgb(0.8, 0.8, 0.7),
+        }
+    }
+}
+
+impl std::fmt::Display for BaseElementType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
             "{}",
-            match self.primary_type {
+            match self {
                 BaseElementType::None => "None",
                 BaseElementType::Fire => "Fire",
                 BaseElementType::Water => "Water",
@@ -224,20 +760,93 @@ impl std::fmt::Display for DieFace {
     }
 }
 
-#[derive(Resource, serde::Deserialize, Default, Debug, Clone, PartialEq, Reflect)]
-#[reflect(Resource)]
-pub enum BaseElementType {
+/// How a tower picks which enemy in range to shoot at.
+#[derive(Component, serde::Deserialize, Default, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub enum TargetingMode {
+    /// Furthest along the path
     #[default]
-    None, // No element
-    Fire,  // Heat and destruction
-    Water, // Flow and adaptability
-    Earth, // Stability and strength
-    Wind,  // Movement and agility
+    First,
+    /// Least far along the path
+    Last,
+    /// Most health left
+    Strongest,
+    /// Least health left
+    Weakest,
+    /// Nearest to the tower
+    Closest,
 }
 
-#[derive(Resource, Debug, Clone, PartialEq, Reflect)]
+/// How a tower's attacks travel to and hurt enemies.
+#[derive(serde::Deserialize, Default, Debug, Clone, PartialEq, Reflect)]
+pub enum ProjectileKind {
+    /// Follows its target until it hits
+    #[default]
+    Homing,
+    /// Lobbed at where its target was, hurting every enemy near where it lands
+    Arcing { height: f32, splash_radius: f32 },
+    /// Hits its target the moment it's fired
+    Beam,
+    /// Hits its target, then jumps on to the nearest enemy within range that hasn't been hit
+    Chain { jumps: u32, range: f32 },
+    /// Flies straight on through enemies, until it has hit as many as it can
+    Pierce { hits: u32 },
+}
+
+/// How an enemy gets from its spawner to its goal.
+#[derive(serde::Deserialize, Default, Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum MovementType {
+    /// Walks along the navmesh, around obstacles
+    #[default]
+    Ground,
+    /// Flies straight to the goal, over obstacles
+    Flying,
+}
+
+impl TargetingMode {
+    fn next(self) -> Self {
+        match self {
+            TargetingMode::First => TargetingMode::Last,
+            TargetingMode::Last => TargetingMode::Strongest,
+            TargetingMode::Strongest => TargetingMode::Weakest,
+            TargetingMode::Weakest => TargetingMode::Closest,
+            TargetingMode::Closest => TargetingMode::First,
+        }
+    }
+}
+
+impl std::fmt::Display for TargetingMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                TargetingMode::First => "First",
+                TargetingMode::Last => "Last",
+                TargetingMode::Strongest => "Strongest",
+                TargetingMode::Weakest => "Weakest",
+                TargetingMode::Closest => "Closest",
+            }
+        )
+    }
+}
+
+#[derive(
+    Resource,
+    serde::Deserialize,
+    Default,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Reflect,
+)]
 #[reflect(Resource)]
-enum Rarity {
+pub enum Rarity {
+    #[default]
     Common,
     Uncommon,
     Rare,
@@ -245,21 +854,127 @@ enum Rarity {
     Unique,
 }
 
+impl std::fmt::Display for Rarity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Rarity {
+    /// The rarity a tower becomes when towers of this rarity are merged, if there is one.
+    fn next(self) -> Option<Self> {
+        match self {
+            Rarity::Common => Some(Rarity::Uncommon),
+            Rarity::Uncommon => Some(Rarity::Rare),
+            Rarity::Rare => Some(Rarity::Epic),
+            Rarity::Epic => Some(Rarity::Unique),
+            Rarity::Unique => None,
+        }
+    }
+
+    // how much a tower of this rarity's damage is multiplied by
+    fn damage_multiplier(self) -> f32 {
+        match self {
+            Rarity::Common => 1.0,
+            Rarity::Uncommon => 1.25,
+            Rarity::Rare => 1.5,
+            Rarity::Epic => 2.0,
+            Rarity::Unique => 3.0,
+        }
+    }
+
+    // range a tower of this rarity gains
+    fn range_bonus(self) -> f32 {
+        match self {
+            Rarity::Common => 0.0,
+            Rarity::Uncommon => 0.25,
+            Rarity::Rare => 0.5,
+            Rarity::Epic => 0.75,
+            Rarity::Unique => 1.0,
+        }
+    }
+
+    // damage a tower gains for each cell of its footprint with a face of this rarity
+    fn bonus_damage(&self) -> u32 {
+        match self {
+            Rarity::Common => 0,
+            Rarity::Uncommon => 1,
+            Rarity::Rare => 2,
+            Rarity::Epic => 3,
+            Rarity::Unique => 5,
+        }
+    }
+
+    // what a face of this rarity adds to the price of a die, and costs to reforge a face to
+    fn value(&self) -> usize {
+        match self {
+            Rarity::Common => 0,
+            Rarity::Uncommon => 3,
+            Rarity::Rare => 8,
+            Rarity::Epic => 15,
+            Rarity::Unique => 30,
+        }
+    }
+}
+
+/// How likely each element and rarity is to turn up on the faces of new dice, loaded from
+/// `game.ron`.
+#[derive(Asset, Debug, TypePath)]
+pub struct FaceWeights {
+    // chance of each face being the die's own element, rather than one drawn from `elements`
+    main_element: f64,
+    // relative weight of each element the other faces are drawn from
+    elements: Vec<(BaseElementType, u32)>,
+    // relative weight of each rarity
+    rarities: Vec<(Rarity, u32)>,
+}
+
+impl FaceWeights {
+    // a random face for a die of the given element
+    fn face(&self, main_element: &BaseElementType, rng: &mut impl Rng) -> DieFace {
+        let primary_type = if rng.gen_bool(self.main_element.clamp(0.0, 1.0)) {
+            main_element.clone()
+        } else {
+            self.elements
+                .choose_weighted(rng, |(_, weight)| *weight)
+                .map(|(element, _)| element.clone())
+                .unwrap_or_else(|_| main_element.clone())
+        };
+        let rarity = self
+            .rarities
+            .choose_weighted(rng, |(_, weight)| *weight)
+            .map(|(rarity, _)| *rarity)
+            .unwrap_or(Rarity::Common);
+        DieFace {
+            primary_type,
+            rarity,
+        }
+    }
+}
+
 #[derive(Event)]
 struct DiePurchaseEvent(Die);
 
 #[derive(Event)]
-struct DieRolledEvent(DieFace);
+struct DieRolledEvent {
+    die: Die,
+    face: DieFace,
+}
 
 #[derive(Resource, Debug, Clone, PartialEq, Reflect)]
 #[reflect(Resource)]
 struct Die {
     // the faces of the die
     faces: [DieFace; 6],
+    // the net the die unfolds into, which becomes the footprint of the towers it rolls
+    net: Hexomino,
     // the current monetary value of the die
     value: usize,
 }
 
+// what a die is worth before its faces' rarities are taken into account
+const DIE_BASE_VALUE: usize = 20;
+
 impl Die {
     fn roll(&self) -> DieFace {
         let mut rng = rand::thread_rng();
@@ -272,7 +987,8 @@ impl std::fmt::Display for Die {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Die with faces: {},{},{},{},{},{}",
+            "Die ({:?}) with faces: {},{},{},{},{},{}",
+            self.net,
             self.faces[0],
             self.faces[1],
             self.faces[2],
@@ -285,30 +1001,44 @@ impl std::fmt::Display for Die {
 
 struct DieBuilder {
     faces: [DieFace; 6],
+    value: usize,
 }
 
 impl DieBuilder {
-    pub fn from_type(selected_type: BaseElementType) -> Self {
-        let face = DieFace {
-            primary_type: selected_type,
-            rarity: Rarity::Common, // todo: make this random
-        };
+    /// A die mostly of the given element, its faces drawn from the weights.
+    pub fn from_type(selected_type: BaseElementType, weights: &FaceWeights) -> Self {
+        let mut rng = rand::thread_rng();
         DieBuilder {
-            faces: [
-                face.clone(),
-                face.clone(),
-                face.clone(),
-                face.clone(),
-                face.clone(),
-                face,
-            ],
+            faces: std::array::from_fn(|_| weights.face(&selected_type, &mut rng)),
+            value: DIE_BASE_VALUE,
+        }
+    }
+
+    /// Raises any face less rare than the given rarity to it.
+    fn min_rarity(mut self, rarity: Rarity) -> Self {
+        for face in self.faces.iter_mut() {
+            face.rarity = face.rarity.max(rarity);
         }
+        self
+    }
+
+    /// Sets what the die is worth before its faces' rarities are taken into account.
+    fn value(mut self, value: usize) -> Self {
+        self.value = value;
+        self
     }
 
     fn build(self) -> Die {
+        let value = self.value
+            + self
+                .faces
+                .iter()
+                .map(|face| face.rarity.value())
+                .sum::<usize>();
         Die {
             faces: self.faces,
-            value: 20,
+            net: *Hexomino::ALL.choose(&mut rand::thread_rng()).unwrap(),
+            value,
         }
     }
 }
@@ -321,17 +1051,35 @@ struct DiePool {
 }
 
 impl DiePool {
-    // remove the die from the pool and return the rolled face
-    fn roll(&mut self) -> DieFace {
+    // remove the die from the pool and return it with the rolled face
+    fn roll(&mut self) -> (Die, DieFace) {
         let idx = self.highlighted;
         let die = self.dice.remove(idx);
-        die.roll()
+        if self.highlighted >= self.dice.len() {
+            self.highlighted = 0;
+        }
+        let face = die.roll();
+        (die, face)
     }
 }
 
+/// A tower rolled from a die, waiting to be placed.
+#[derive(Debug, Clone, PartialEq)]
+struct PooledTower {
+    details: AssetId<TowerDetails>,
+    // the net of the die it was rolled from
+    net: Hexomino,
+    // the faces of that die, in the order of the net's cells
+    faces: [DieFace; 6],
+    // what the die cost, part of which is refunded if the tower is sold
+    value: usize,
+    // the rarity of the face it was rolled on, raised by merging
+    rarity: Rarity,
+}
+
 #[derive(Resource, Default, Debug, PartialEq)]
 struct TowerPool {
-    towers: Vec<AssetId<TowerDetails>>,
+    towers: Vec<PooledTower>,
     highlighted: usize,
 }
 
@@ -342,16 +1090,33 @@ impl TowerPool {
         }
         self.highlighted = (self.highlighted + 1) % self.towers.len();
     }
+
+    // remove a tower, keeping the same tower highlighted unless it's the one removed
+    fn remove(&mut self, index: usize) -> PooledTower {
+        let tower = self.towers.remove(index);
+        if index < self.highlighted {
+            self.highlighted -= 1;
+        }
+        if self.highlighted >= self.towers.len() {
+            self.highlighted = 0;
+        }
+        tower
+    }
+
+    // remove the highlighted tower, keeping the highlight within the pool
+    fn remove_highlighted(&mut self) {
+        self.towers.remove(self.highlighted);
+        if self.highlighted >= self.towers.len() {
+            self.highlighted = 0;
+        }
+    }
 }
 
-fn setup(
-    mut commands: Commands,
-    assets_gltfmesh: Res<Assets<GltfMesh>>,
-    mut assets_mesh: ResMut<Assets<Mesh>>,
-    assets_enemydetails: Res<Assets<EnemyDetails>>,
-    gltfassets: Res<GltfAssets>,
-    res: Res<Assets<Gltf>>,
-) {
+fn setup(mut commands: Commands) {
+    // start every game with empty pools
+    commands.insert_resource(DiePool::default());
+    commands.insert_resource(TowerPool::default());
+
     commands.spawn((
         DirectionalLight {
             illuminance: light_consts::lux::OVERCAST_DAY,
@@ -364,65 +1129,8 @@ fn setup(
             ..default()
         },
         Name::new("Directional Light"),
+        OnGameScreen,
     ));
-
-    // get first enemy from assets
-    let enemy = assets_enemydetails.iter().next().unwrap();
-    let enemy_mesh = res.get(&enemy.1.model).unwrap();
-    let enemy_mesh_mesh = assets_gltfmesh.get(&enemy_mesh.meshes[0]).unwrap();
-
-    commands.spawn((
-        Mesh3d(enemy_mesh_mesh.primitives[0].mesh.clone()),
-        MeshMaterial3d(enemy_mesh.materials[0].clone()),
-        Transform::from_translation(Vec3::new(0.5, 0.0, -10.0)),
-        EnemySpawner {
-            timer: Timer::new(Duration::from_secs(1), TimerMode::Repeating),
-        },
-    ));
-
-    let house_mesh = res.get(&gltfassets.house).unwrap();
-    let house_mesh_mats = assets_gltfmesh.get(&house_mesh.meshes[0]).unwrap();
-
-    commands.spawn((
-        Mesh3d(house_mesh_mats.primitives[0].mesh.clone()),
-        MeshMaterial3d(house_mesh.materials[0].clone()),
-        Transform::from_translation(Vec3::new(-5.8, 0.0, -4.0))
-            .with_rotation(Quat::from_rotation_y(std::f32::consts::FRAC_PI_2))
-            .with_scale(Vec3::splat(0.25)),
-        Obstacle,
-        Name::new("House"),
-    ));
-
-    // spawn square placeholder for goal
-    commands.spawn((
-        Mesh3d(assets_mesh.add(Rectangle::new(0.1, 1.0))),
-        Transform::default()
-            .with_translation(Vec3::new(-3.9, 0.0, -1.5))
-            .with_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)),
-        Goal,
-    ));
-
-    commands.spawn((
-        NavMeshSettings {
-            // Define the outer borders of the navmesh.
-            fixed: Triangulation::from_outer_edges(&[
-                vec2(-20.0, -20.0),
-                vec2(20.0, -20.0),
-                vec2(20.0, 20.0),
-                vec2(-20.0, 20.0),
-            ]),
-            ..default()
-        },
-        // Mark it for update as soon as obstacles are changed.
-        // Other modes can be debounced or manually triggered.
-        NavMeshUpdateMode::Direct,
-        Transform::from_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)),
-    ));
-}
-
-#[derive(Default, Component)]
-struct Wave {
-    timer: Timer,
 }
 
 fn die_purchased(mut die_pool: ResMut<DiePool>, mut ev_purchased: EventReader<DiePurchaseEvent>) {
@@ -437,14 +1145,22 @@ fn die_rolled(
     mut ev_rolled: EventReader<DieRolledEvent>,
 ) {
     for ev in ev_rolled.read() {
-        let face = ev.0.clone();
-        let selected_type = face.primary_type.clone();
-        let (id, tower) = tower_assets
+        let selected_type = ev.face.primary_type.clone();
+        let Some((id, tower)) = tower_assets
             .iter()
             .filter(|(_, tower)| tower.element_type == selected_type)
             .choose(&mut rand::thread_rng())
-            .unwrap();
+        else {
+            warn!("no tower for rolled element: {:?}", selected_type);
+            continue;
+        };
         info!("Selected tower: {}", tower.name);
-        tower_pool.towers.push(id);
+        tower_pool.towers.push(PooledTower {
+            details: id,
+            net: ev.die.net,
+            faces: ev.die.faces.clone(),
+            value: ev.die.value,
+            rarity: ev.face.rarity,
+        });
     }
 }
@@ -1,9 +1,14 @@
 use bevy::prelude::*;
 use leafwing_input_manager::{prelude::*, Actionlike, InputControlKind};
+use rand::{seq::SliceRandom, Rng};
 
 use crate::{despawn_screen, GameState};
 
-use super::{BaseElementType, Die, DieBuilder, DiePurchaseEvent, GamePlayState};
+use super::{
+    wave::{WaveCompletedEvent, WaveCounter, WaveStartedEvent},
+    AllAssets, BaseElementType, Die, DieBuilder, DiePool, DiePurchaseEvent, EconomyDetails,
+    FaceWeights, GamePlayState, ShopDetails,
+};
 
 pub struct EconomyPlugin;
 
@@ -12,25 +17,35 @@ impl Plugin for EconomyPlugin {
         app.add_plugins(InputManagerPlugin::<EconomyAction>::default())
             .init_resource::<ActionState<EconomyAction>>()
             .insert_resource(EconomyAction::default_input_map())
-            .insert_resource(Economy { money: 50 })
-            .insert_resource(DieShop {
-                highlighted: 0,
-                items: vec![
-                    DieBuilder::from_type(BaseElementType::Fire).build(),
-                    DieBuilder::from_type(BaseElementType::Water).build(),
-                    DieBuilder::from_type(BaseElementType::Earth).build(),
-                    DieBuilder::from_type(BaseElementType::Wind).build(),
-                ],
-            })
-            .add_systems(OnEnter(GameState::Game), economy_setup)
+            .init_resource::<Economy>()
+            .init_resource::<WaveIncome>()
+            .init_resource::<DieShop>()
+            .init_resource::<Forge>()
+            .add_systems(OnEnter(GameState::Game), reset_economy)
+            .add_systems(OnEnter(GamePlayState::Economy), economy_setup)
+            .add_systems(
+                Update,
+                (restock_shop, reset_wave_income, collect_wave_income)
+                    .run_if(in_state(GameState::Game)),
+            )
             .add_systems(
                 Update,
-                (choose_die, display_shop, start_rolling)
+                (
+                    choose_die,
+                    reroll_shop,
+                    reforge_die,
+                    display_shop,
+                    display_income,
+                    start_rolling,
+                )
                     .run_if(in_state(GamePlayState::Economy).and(in_state(GameState::Game))),
             )
             .add_systems(
                 OnExit(GamePlayState::Economy),
-                despawn_screen::<DieShopOverlay>,
+                (
+                    despawn_screen::<DieShopOverlay>,
+                    despawn_screen::<IncomeOverlay>,
+                ),
             );
     }
 }
@@ -41,6 +56,11 @@ enum EconomyAction {
     ToggleDieLeft,
     ToggleDieRight,
     BuyDie,
+    RerollShop,
+    ToggleOwnedDie,
+    ToggleFace,
+    ReforgeElement,
+    ReforgeRarity,
     PlacementPhase,
 }
 
@@ -50,6 +70,11 @@ impl Actionlike for EconomyAction {
             EconomyAction::ToggleDieLeft => InputControlKind::Button,
             EconomyAction::ToggleDieRight => InputControlKind::Button,
             EconomyAction::BuyDie => InputControlKind::Button,
+            EconomyAction::RerollShop => InputControlKind::Button,
+            EconomyAction::ToggleOwnedDie => InputControlKind::Button,
+            EconomyAction::ToggleFace => InputControlKind::Button,
+            EconomyAction::ReforgeElement => InputControlKind::Button,
+            EconomyAction::ReforgeRarity => InputControlKind::Button,
             EconomyAction::PlacementPhase => InputControlKind::Button,
         }
     }
@@ -64,77 +89,464 @@ impl EconomyAction {
         input_map.insert(Self::ToggleDieLeft, GamepadButton::DPadLeft);
         input_map.insert(Self::ToggleDieRight, GamepadButton::DPadRight);
         input_map.insert(Self::BuyDie, GamepadButton::East);
+        input_map.insert(Self::RerollShop, GamepadButton::LeftTrigger);
+        input_map.insert(Self::ToggleOwnedDie, GamepadButton::DPadUp);
+        input_map.insert(Self::ToggleFace, GamepadButton::DPadDown);
+        input_map.insert(Self::ReforgeElement, GamepadButton::North);
+        input_map.insert(Self::ReforgeRarity, GamepadButton::West);
         input_map.insert(Self::PlacementPhase, GamepadButton::South);
 
         // Default kbm input bindings
         input_map.insert(Self::ToggleDieLeft, KeyCode::KeyQ);
         input_map.insert(Self::ToggleDieRight, KeyCode::KeyE);
         input_map.insert(Self::BuyDie, KeyCode::Space);
+        input_map.insert(Self::RerollShop, KeyCode::KeyX);
+        input_map.insert(Self::ToggleOwnedDie, KeyCode::Tab);
+        input_map.insert(Self::ToggleFace, KeyCode::KeyF);
+        input_map.insert(Self::ReforgeElement, KeyCode::KeyR);
+        input_map.insert(Self::ReforgeRarity, KeyCode::KeyU);
         input_map.insert(Self::PlacementPhase, KeyCode::Enter);
 
         input_map
     }
 }
 
-#[derive(Resource)]
+// cost of changing a face's element, raising its rarity costs what the new rarity is worth
+const REFORGE_ELEMENT_COST: usize = 5;
+
+// elements a face can be reforged into, in the order they're cycled through
+const REFORGE_ELEMENTS: [BaseElementType; 4] = [
+    BaseElementType::Fire,
+    BaseElementType::Water,
+    BaseElementType::Earth,
+    BaseElementType::Wind,
+];
+
+#[derive(Resource, Default)]
 pub struct Economy {
     pub money: usize,
 }
 
-#[derive(Resource, Debug, Clone, PartialEq)]
+/// Money earned over the last wave, by where it came from.
+#[derive(Resource, Default, Debug)]
+pub struct WaveIncome {
+    // the wave the income is earned in, none until the first wave starts
+    wave: Option<usize>,
+    // money held when the wave started, which interest is paid on
+    banked: usize,
+    pub bounties: usize,
+    pub boss_bounties: usize,
+    reward: usize,
+    clear_bonus: usize,
+    interest: usize,
+    perfect_bonus: usize,
+    // enemies that reached a goal, any of which loses the perfect wave bonus
+    pub leaked: u32,
+}
+
+impl WaveIncome {
+    fn total(&self) -> usize {
+        self.bounties
+            + self.boss_bounties
+            + self.reward
+            + self.clear_bonus
+            + self.interest
+            + self.perfect_bonus
+    }
+}
+
+/// The dice on offer, drawn from the shop's details after every wave.
+#[derive(Resource, Default, Debug, Clone, PartialEq)]
 struct DieShop {
-    items: Vec<Die>,
+    offers: Vec<ShopOffer>,
     highlighted: usize,
+    // rerolls bought since the last restock, each one costing more
+    rerolls: usize,
+    // dice of each shop item left before it sells out, until the next restock
+    stock: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ShopOffer {
+    // index of the shop item it was drawn from
+    item: usize,
+    // the die that's bought next, replaced by a fresh one of the same kind after each sale
+    die: Die,
+    price: usize,
+    on_sale: bool,
+}
+
+impl ShopOffer {
+    fn new(item: usize, details: &ShopDetails, weights: &FaceWeights, on_sale: bool) -> Self {
+        let shop_item = &details.items[item];
+        let die = DieBuilder::from_type(shop_item.element.clone(), weights)
+            .min_rarity(shop_item.min_rarity)
+            .value(shop_item.price)
+            .build();
+        let price = if on_sale {
+            die.value * (100 - details.sale_percent.min(100)) / 100
+        } else {
+            die.value
+        };
+        ShopOffer {
+            item,
+            die,
+            price,
+            on_sale,
+        }
+    }
+}
+
+impl DieShop {
+    // refills every item's stock and draws fresh offers, at the start of the game and after
+    // every wave
+    fn restock(&mut self, details: &ShopDetails, weights: &FaceWeights, waves_cleared: usize) {
+        self.stock = details.items.iter().map(|item| item.stock).collect();
+        self.rerolls = 0;
+        self.draw_offers(details, weights, waves_cleared);
+    }
+
+    // replaces the offers with ones drawn from the items unlocked by the waves cleared that
+    // haven't sold out
+    fn draw_offers(&mut self, details: &ShopDetails, weights: &FaceWeights, waves_cleared: usize) {
+        let mut rng = rand::thread_rng();
+        let available: Vec<usize> = (0..details.items.len())
+            .filter(|item| {
+                details.items[*item].unlock_wave <= waves_cleared && self.stock[*item] > 0
+            })
+            .collect();
+        self.offers = (0..details.offers)
+            .filter_map(|_| {
+                let item = *available
+                    .choose_weighted(&mut rng, |item| details.items[*item].weight)
+                    .ok()?;
+                let on_sale = rng.gen_bool(details.sale_chance.clamp(0.0, 1.0));
+                Some(ShopOffer::new(item, details, weights, on_sale))
+            })
+            .collect();
+        self.highlighted = 0;
+    }
+
+    // dice of the offer's item left before it sells out
+    fn stock(&self, offer: &ShopOffer) -> usize {
+        self.stock[offer.item]
+    }
+
+    fn reroll_cost(&self, details: &ShopDetails) -> usize {
+        details.reroll_cost + self.rerolls * details.reroll_cost_increase
+    }
+}
+
+/// The face of the highlighted owned die that's being reforged.
+#[derive(Resource, Default, Debug)]
+struct Forge {
+    face: usize,
 }
 
 #[derive(Component)]
 pub struct DieShopOverlay;
 
+#[derive(Component)]
+struct IncomeOverlay;
+
+fn reset_economy(
+    mut economy: ResMut<Economy>,
+    mut shop: ResMut<DieShop>,
+    mut forge: ResMut<Forge>,
+    mut income: ResMut<WaveIncome>,
+    all_assets: Res<AllAssets>,
+    assets_economy: Res<Assets<EconomyDetails>>,
+    assets_shop: Res<Assets<ShopDetails>>,
+    assets_weights: Res<Assets<FaceWeights>>,
+) {
+    economy.money = assets_economy
+        .get(&all_assets.economy)
+        .unwrap()
+        .starting_money;
+    *income = WaveIncome::default();
+    // every game starts with a freshly stocked shop
+    let details = assets_shop.get(&all_assets.shop).unwrap();
+    let weights = assets_weights.get(&all_assets.face_weights).unwrap();
+    shop.restock(details, weights, 0);
+    *forge = Forge::default();
+}
+
+// rotates in new offers after every wave, including any the wave unlocked
+fn restock_shop(
+    mut shop: ResMut<DieShop>,
+    all_assets: Res<AllAssets>,
+    assets_shop: Res<Assets<ShopDetails>>,
+    assets_weights: Res<Assets<FaceWeights>>,
+    mut ev_completed: EventReader<WaveCompletedEvent>,
+) {
+    for ev in ev_completed.read() {
+        let details = assets_shop.get(&all_assets.shop).unwrap();
+        let weights = assets_weights.get(&all_assets.face_weights).unwrap();
+        shop.restock(details, weights, ev.wave + 1);
+    }
+}
+
 fn economy_setup(mut commands: Commands) {
     commands.spawn((Text::default(), DieShopOverlay));
+    commands.spawn((
+        Text::default(),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        },
+        IncomeOverlay,
+    ));
+}
+
+// starts a fresh tally for every wave
+fn reset_wave_income(
+    economy: Res<Economy>,
+    mut income: ResMut<WaveIncome>,
+    mut ev_started: EventReader<WaveStartedEvent>,
+) {
+    for ev in ev_started.read() {
+        *income = WaveIncome {
+            wave: Some(ev.0),
+            banked: economy.money,
+            ..default()
+        };
+    }
+}
+
+// pays out the wave's reward and bonuses once it's cleared, interest being on the money
+// banked going into the wave, so the wave's own bounties don't earn any
+fn collect_wave_income(
+    mut economy: ResMut<Economy>,
+    mut income: ResMut<WaveIncome>,
+    all_assets: Res<AllAssets>,
+    assets_economy: Res<Assets<EconomyDetails>>,
+    mut ev_completed: EventReader<WaveCompletedEvent>,
+) {
+    for ev in ev_completed.read() {
+        let details = assets_economy.get(&all_assets.economy).unwrap();
+        income.interest =
+            (income.banked * details.interest_percent / 100).min(details.interest_cap);
+        income.reward = ev.reward;
+        income.clear_bonus = details.wave_clear_bonus;
+        income.perfect_bonus = if income.leaked == 0 {
+            details.perfect_wave_bonus
+        } else {
+            0
+        };
+        // bounties were paid as the enemies died
+        economy.money +=
+            income.interest + income.reward + income.clear_bonus + income.perfect_bonus;
+        info!("Wave {} earned {}", ev.wave + 1, income.total());
+    }
 }
 
 fn choose_die(
     action_state: Res<ActionState<EconomyAction>>,
     mut economy: ResMut<Economy>,
     mut shop: ResMut<DieShop>,
+    all_assets: Res<AllAssets>,
+    assets_shop: Res<Assets<ShopDetails>>,
+    assets_weights: Res<Assets<FaceWeights>>,
     mut ev_die_purchase: EventWriter<DiePurchaseEvent>,
 ) {
+    if shop.offers.is_empty() {
+        return;
+    }
     if action_state.just_pressed(&EconomyAction::ToggleDieLeft) {
-        shop.highlighted = (shop.highlighted + shop.items.len() - 1) % shop.items.len();
+        shop.highlighted = (shop.highlighted + shop.offers.len() - 1) % shop.offers.len();
     }
     if action_state.just_pressed(&EconomyAction::ToggleDieRight) {
-        shop.highlighted = (shop.highlighted + 1) % shop.items.len();
+        shop.highlighted = (shop.highlighted + 1) % shop.offers.len();
     }
     // Buy the die, remove costs, add to diepool resource
     if action_state.just_pressed(&EconomyAction::BuyDie) {
-        let cost = shop.items[shop.highlighted].value;
-        if economy.money < cost {
+        let highlighted = shop.highlighted;
+        let offer = shop.offers[highlighted].clone();
+        if shop.stock(&offer) == 0 {
+            info!("Sold out");
+            return;
+        }
+        if economy.money < offer.price {
+            return;
+        }
+        economy.money -= offer.price;
+        shop.stock[offer.item] -= 1;
+        ev_die_purchase.send(DiePurchaseEvent(offer.die));
+        // the next one of its kind gets freshly rolled faces
+        let details = assets_shop.get(&all_assets.shop).unwrap();
+        let weights = assets_weights.get(&all_assets.face_weights).unwrap();
+        shop.offers[highlighted] = ShopOffer::new(offer.item, details, weights, offer.on_sale);
+    }
+}
+
+// swaps the offers for new ones, for a price that rises with every reroll until the restock,
+// without refilling the stock of anything already bought
+fn reroll_shop(
+    action_state: Res<ActionState<EconomyAction>>,
+    mut economy: ResMut<Economy>,
+    mut shop: ResMut<DieShop>,
+    all_assets: Res<AllAssets>,
+    assets_shop: Res<Assets<ShopDetails>>,
+    assets_weights: Res<Assets<FaceWeights>>,
+    wave_counter: Res<WaveCounter>,
+) {
+    if !action_state.just_pressed(&EconomyAction::RerollShop) {
+        return;
+    }
+    let details = assets_shop.get(&all_assets.shop).unwrap();
+    let weights = assets_weights.get(&all_assets.face_weights).unwrap();
+    let cost = shop.reroll_cost(details);
+    if economy.money < cost {
+        info!("Can't afford to reroll the shop");
+        return;
+    }
+    economy.money -= cost;
+    shop.rerolls += 1;
+    shop.draw_offers(details, weights, wave_counter.current);
+}
+
+// changes the element or raises the rarity of a face of one of the dice already bought
+fn reforge_die(
+    action_state: Res<ActionState<EconomyAction>>,
+    mut economy: ResMut<Economy>,
+    mut die_pool: ResMut<DiePool>,
+    mut forge: ResMut<Forge>,
+) {
+    if die_pool.dice.is_empty() {
+        return;
+    }
+    if action_state.just_pressed(&EconomyAction::ToggleOwnedDie) {
+        die_pool.highlighted = (die_pool.highlighted + 1) % die_pool.dice.len();
+    }
+    if action_state.just_pressed(&EconomyAction::ToggleFace) {
+        forge.face = (forge.face + 1) % 6;
+    }
+    let highlighted = die_pool.highlighted;
+    let die = &mut die_pool.dice[highlighted];
+    let face = &mut die.faces[forge.face];
+    if action_state.just_pressed(&EconomyAction::ReforgeElement) {
+        if economy.money < REFORGE_ELEMENT_COST {
+            info!("Can't afford to reforge {}", face);
+            return;
+        }
+        let current = REFORGE_ELEMENTS
+            .iter()
+            .position(|element| *element == face.primary_type)
+            .unwrap_or(REFORGE_ELEMENTS.len() - 1);
+        face.primary_type = REFORGE_ELEMENTS[(current + 1) % REFORGE_ELEMENTS.len()].clone();
+        economy.money -= REFORGE_ELEMENT_COST;
+        die.value += REFORGE_ELEMENT_COST;
+    }
+    if action_state.just_pressed(&EconomyAction::ReforgeRarity) {
+        let Some(next) = face.rarity.next() else {
+            info!("{} is already as rare as it gets", face);
+            return;
+        };
+        if economy.money < next.value() {
+            info!("Can't afford to raise {} to {}", face, next);
             return;
         }
-        economy.money -= cost;
-        ev_die_purchase.send(DiePurchaseEvent(shop.items[shop.highlighted].clone()));
+        face.rarity = next;
+        economy.money -= next.value();
+        die.value += next.value();
     }
 }
 
 fn display_shop(
     shop: Res<DieShop>,
     economy: Res<Economy>,
+    all_assets: Res<AllAssets>,
+    assets_shop: Res<Assets<ShopDetails>>,
+    die_pool: Res<DiePool>,
+    forge: Res<Forge>,
     mut query: Query<(&mut Text, &DieShopOverlay)>,
 ) {
+    let forge_text = match die_pool.dice.get(die_pool.highlighted) {
+        Some(die) => {
+            let faces = die
+                .faces
+                .iter()
+                .enumerate()
+                .map(|(i, face)| {
+                    if i == forge.face {
+                        format!("[{}]", face)
+                    } else {
+                        face.to_string()
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            let rarity = match die.faces[forge.face].rarity.next() {
+                Some(next) => format!("Raise to {}: {} (U)", next, next.value()),
+                None => "Can't be raised any further".to_string(),
+            };
+            format!(
+                "Forge ({}/{})\nDie ({:?}): {}\nChange element: {} (R)\n{}",
+                die_pool.highlighted + 1,
+                die_pool.dice.len(),
+                die.net,
+                faces,
+                REFORGE_ELEMENT_COST,
+                rarity,
+            )
+        }
+        None => "Forge\nBuy a die to reforge its faces".to_string(),
+    };
+    let reroll_cost = shop.reroll_cost(assets_shop.get(&all_assets.shop).unwrap());
     for (mut text, _) in query.iter_mut() {
         text.0 = format!(
-            "Shop\nMoney: {}\n\n{}",
+            "Shop\nMoney: {}\nReroll: {} (X)\n\n{}\n\n{}",
             economy.money,
-            shop.items
+            reroll_cost,
+            shop.offers
                 .iter()
                 .enumerate()
-                .map(|(i, item)| {
+                .map(|(i, offer)| {
                     let prefix = if i == shop.highlighted { ">> " } else { "   " };
-                    format!("{}{}", prefix, item)
+                    let stock = shop.stock(offer);
+                    let price = if stock == 0 {
+                        "SOLD OUT".to_string()
+                    } else if offer.on_sale {
+                        format!(
+                            "Cost: {} (SALE, was {}), {} left",
+                            offer.price, offer.die.value, stock
+                        )
+                    } else {
+                        format!("Cost: {}, {} left", offer.price, stock)
+                    };
+                    format!("{}{}\n   {}", prefix, offer.die, price)
                 })
                 .collect::<Vec<String>>()
-                .join("\n\n")
+                .join("\n\n"),
+            forge_text,
+        );
+    }
+}
+
+// breaks down what the last wave earned
+fn display_income(income: Res<WaveIncome>, mut query: Query<&mut Text, With<IncomeOverlay>>) {
+    let Some(wave) = income.wave else {
+        return;
+    };
+    let perfect = if income.leaked == 0 {
+        format!("Perfect wave: {}", income.perfect_bonus)
+    } else {
+        format!("Perfect wave: missed, {} got through", income.leaked)
+    };
+    for mut text in query.iter_mut() {
+        text.0 = format!(
+            "Wave {} cleared\n\nKills: {}\nBoss: {}\nWave reward: {}\nClear bonus: {}\n\
+             Interest: {}\n{}\n\nTotal: {}",
+            wave + 1,
+            income.bounties,
+            income.boss_bounties,
+            income.reward,
+            income.clear_bonus,
+            income.interest,
+            perfect,
+            income.total(),
         );
     }
 }
@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+
+use super::{
+    status::{StackingRule, StatusEffect, StatusEffectKind},
+    BaseElementType, DieFace,
+};
+
+/// The 11 hexominoes that fold up into a cube, used as the base shape of a tower.
+///
+/// The `OneFourOne` nets are a row of four with one cell above and one below,
+/// the `TwoThreeOne` nets are rows of two, three and one cells.
+#[derive(serde::Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum Hexomino {
+    #[default]
+    OneFourOneA,
+    OneFourOneB,
+    OneFourOneC,
+    OneFourOneD,
+    OneFourOneE,
+    OneFourOneF,
+    TwoThreeOneA,
+    TwoThreeOneB,
+    TwoThreeOneC,
+    TwoTwoTwo,
+    ThreeThree,
+}
+
+impl Hexomino {
+    pub const ALL: [Hexomino; 11] = [
+        Hexomino::OneFourOneA,
+        Hexomino::OneFourOneB,
+        Hexomino::OneFourOneC,
+        Hexomino::OneFourOneD,
+        Hexomino::OneFourOneE,
+        Hexomino::OneFourOneF,
+        Hexomino::TwoThreeOneA,
+        Hexomino::TwoThreeOneB,
+        Hexomino::TwoThreeOneC,
+        Hexomino::TwoTwoTwo,
+        Hexomino::ThreeThree,
+    ];
+
+    // cells of the net relative to its centre cell, x along the world x axis and y along z.
+    // cell i is where face i of a die ends up when it's unfolded, so folding the net back up
+    // puts faces i and 5 - i opposite each other, as on a real die
+    fn cells(&self) -> [(i32, i32); 6] {
+        match self {
+            Hexomino::OneFourOneA => [(0, 0), (-1, 0), (-1, 1), (-1, -1), (1, 0), (2, 0)],
+            Hexomino::OneFourOneB => [(0, 0), (-1, 0), (-1, 1), (0, -1), (1, 0), (2, 0)],
+            Hexomino::OneFourOneC => [(0, 0), (-1, 0), (-1, 1), (1, -1), (1, 0), (2, 0)],
+            Hexomino::OneFourOneD => [(0, 0), (-1, 0), (-1, 1), (2, -1), (1, 0), (2, 0)],
+            Hexomino::OneFourOneE => [(0, 0), (-1, 0), (0, 1), (0, -1), (1, 0), (2, 0)],
+            Hexomino::OneFourOneF => [(0, 0), (-1, 0), (0, 1), (1, -1), (1, 0), (2, 0)],
+            Hexomino::TwoThreeOneA => [(0, 0), (-1, -1), (-1, 0), (1, 0), (0, 1), (0, 2)],
+            Hexomino::TwoThreeOneB => [(0, 0), (-1, -1), (-1, 0), (1, 1), (0, 1), (0, 2)],
+            Hexomino::TwoThreeOneC => [(0, 0), (-1, -1), (0, -1), (0, 1), (1, 1), (-1, -2)],
+            Hexomino::TwoTwoTwo => [(0, 0), (-1, -1), (-1, 0), (1, 1), (0, 1), (1, 2)],
+            Hexomino::ThreeThree => [(0, 0), (0, -1), (1, 0), (1, 2), (1, 1), (0, -2)],
+        }
+    }
+}
+
+/// A hexomino as rotated and mirrored by the player.
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct Footprint {
+    pub shape: Hexomino,
+    // quarter turns
+    pub rotation: u8,
+    pub mirrored: bool,
+}
+
+impl Footprint {
+    pub fn new(shape: Hexomino) -> Self {
+        Footprint {
+            shape,
+            rotation: 0,
+            mirrored: false,
+        }
+    }
+
+    pub fn rotate(&mut self) {
+        self.rotation = (self.rotation + 1) % 4;
+    }
+
+    pub fn mirror(&mut self) {
+        self.mirrored = !self.mirrored;
+    }
+
+    /// Cells covered by the footprint, relative to the cell the tower stands on.
+    /// Cell i carries face i of the die the tower was rolled from.
+    pub fn cells(&self) -> [IVec2; 6] {
+        self.shape.cells().map(|(x, y)| {
+            let x = if self.mirrored { -x } else { x };
+            (0..self.rotation).fold(IVec2::new(x, y), |cell, _| IVec2::new(-cell.y, cell.x))
+        })
+    }
+}
+
+/// One grid cell covered by a placed tower's footprint.
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub struct FootprintCell {
+    pub cell: IVec2,
+    // the die face that landed on this cell
+    pub face: DieFace,
+}
+
+/// Extra damage and status effects a tower gets from the die faces under its footprint.
+///
+/// Every face adds damage for its rarity, and every face of a different element to the
+/// tower adds that element's effect. Several faces of the same element lengthen the effect.
+pub fn face_bonuses(element: &BaseElementType, faces: &[DieFace]) -> (u32, Vec<StatusEffect>) {
+    let damage = faces.iter().map(|face| face.rarity.bonus_damage()).sum();
+    let mut effects: Vec<StatusEffect> = vec![];
+    for face in faces.iter().filter(|face| face.primary_type != *element) {
+        let Some(effect) = cell_effect(&face.primary_type) else {
+            continue;
+        };
+        match effects
+            .iter_mut()
+            .find(|existing| existing.kind == effect.kind)
+        {
+            Some(existing) => existing.duration += effect.duration,
+            None => effects.push(effect),
+        }
+    }
+    (damage, effects)
+}
+
+// the effect a single cell of an element adds to a tower of another element
+fn cell_effect(element: &BaseElementType) -> Option<StatusEffect> {
+    let (kind, duration, stacking) = match element {
+        BaseElementType::None => return None,
+        BaseElementType::Fire => (
+            StatusEffectKind::Burn {
+                damage_per_second: 1.0,
+            },
+            1.0,
+            StackingRule::Stack { max: 3 },
+        ),
+        BaseElementType::Water => (
+            StatusEffectKind::Slow {
+                speed_multiplier: 0.8,
+            },
+            0.5,
+            StackingRule::Refresh,
+        ),
+        BaseElementType::Earth => (StatusEffectKind::Root, 0.1, StackingRule::Ignore),
+        BaseElementType::Wind => (
+            StatusEffectKind::Knockback { distance: 0.5 },
+            0.2,
+            StackingRule::Ignore,
+        ),
+    };
+    Some(StatusEffect {
+        kind,
+        duration,
+        stacking,
+    })
+}
@@ -0,0 +1,134 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::GameState;
+
+use super::{placement::Tower, SNAP_OFFSET};
+
+pub struct GridPlugin;
+
+impl Plugin for GridPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            vacate_removed_towers.run_if(in_state(GameState::Game)),
+        );
+    }
+}
+
+/// The cells of the map, and what's standing on each of them.
+#[derive(Resource, Debug)]
+pub struct Grid {
+    // first cell of the grid
+    min: IVec2,
+    // one past the last cell of the grid
+    max: IVec2,
+    // the tower covering each occupied cell
+    occupants: HashMap<IVec2, Entity>,
+    // cells towers can never be built on, e.g. under the house or on a spawner
+    unbuildable: HashSet<IVec2>,
+}
+
+impl Grid {
+    /// A grid covering the area between two opposite corners, in world x/z.
+    pub fn new(corner: Vec2, opposite: Vec2) -> Self {
+        Grid {
+            min: corner.min(opposite).floor().as_ivec2(),
+            max: corner.max(opposite).ceil().as_ivec2(),
+            occupants: HashMap::default(),
+            unbuildable: HashSet::default(),
+        }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        (self.max - self.min).as_uvec2()
+    }
+
+    /// The world position of the centre of the grid.
+    pub fn center(&self) -> Vec3 {
+        let center = (self.min + self.max).as_vec2() / 2.0;
+        Vec3::new(center.x, 0.0, center.y)
+    }
+
+    /// Every cell on the grid.
+    pub fn cells(&self) -> impl Iterator<Item = IVec2> + '_ {
+        (self.min.x..self.max.x)
+            .flat_map(move |x| (self.min.y..self.max.y).map(move |y| IVec2::new(x, y)))
+    }
+
+    pub fn contains(&self, cell: IVec2) -> bool {
+        cell.cmpge(self.min).all() && cell.cmplt(self.max).all()
+    }
+
+    /// The nearest cell on the grid to the given one.
+    pub fn clamp(&self, cell: IVec2) -> IVec2 {
+        cell.clamp(self.min, self.max - IVec2::ONE)
+    }
+
+    pub fn occupant(&self, cell: IVec2) -> Option<Entity> {
+        self.occupants.get(&cell).copied()
+    }
+
+    pub fn is_unbuildable(&self, cell: IVec2) -> bool {
+        self.unbuildable.contains(&cell)
+    }
+
+    /// Whether a tower could be built on the cell.
+    pub fn is_buildable(&self, cell: IVec2) -> bool {
+        self.contains(cell) && !self.is_unbuildable(cell) && self.occupant(cell).is_none()
+    }
+
+    pub fn occupy(&mut self, cell: IVec2, entity: Entity) {
+        self.occupants.insert(cell, entity);
+    }
+
+    /// Frees up every cell the entity was occupying.
+    pub fn vacate(&mut self, entity: Entity) {
+        self.occupants.retain(|_, occupant| *occupant != entity);
+    }
+
+    pub fn set_unbuildable(&mut self, cell: IVec2) {
+        self.unbuildable.insert(cell);
+    }
+
+    /// Marks every cell the area between two opposite corners touches, in world x/z.
+    pub fn set_unbuildable_area(&mut self, corner: Vec2, opposite: Vec2) {
+        let min = corner.min(opposite);
+        // an area ending right on the edge of a cell doesn't cover the next one
+        let max = corner.max(opposite) - Vec2::splat(0.01);
+        for x in min.x.floor() as i32..=max.x.floor() as i32 {
+            for y in min.y.floor() as i32..=max.y.floor() as i32 {
+                self.set_unbuildable(IVec2::new(x, y));
+            }
+        }
+    }
+
+    /// Every unbuildable cell on the grid.
+    pub fn unbuildable_cells(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.unbuildable.iter().copied()
+    }
+}
+
+/// The grid cell a world position falls in.
+pub fn cell_at(position: Vec3) -> IVec2 {
+    IVec2::new(position.x.floor() as i32, position.z.floor() as i32)
+}
+
+/// The world position of the centre of a grid cell.
+pub fn cell_center(cell: IVec2) -> Vec3 {
+    Vec3::new(
+        cell.x as f32 + SNAP_OFFSET,
+        0.0,
+        cell.y as f32 + SNAP_OFFSET,
+    )
+}
+
+// keeps the grid in sync with towers that have been despawned, whose footprint obstacles
+// going with them rebuilds the navmesh
+fn vacate_removed_towers(mut grid: ResMut<Grid>, mut removed: RemovedComponents<Tower>) {
+    for entity in removed.read() {
+        grid.vacate(entity);
+    }
+}
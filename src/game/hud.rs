@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+
+use crate::GameState;
+
+use super::{
+    wave::{Boss, Enemy, EnemyPhases, Lives, WaveCounter},
+    AllAssets, BaseElementType, ElementChart, EnemyDetails, OnGameScreen, WaveDetails,
+};
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Game), hud_setup)
+            .add_systems(
+                Update,
+                (display_hud, display_boss_health).run_if(in_state(GameState::Game)),
+            );
+    }
+}
+
+#[derive(Component)]
+struct HudOverlay;
+
+/// The bar along the top of the screen showing the boss's health.
+#[derive(Component)]
+struct BossHealthBar;
+
+#[derive(Component)]
+struct BossHealthFill;
+
+#[derive(Component)]
+struct BossHealthText;
+
+fn hud_setup(mut commands: Commands) {
+    commands.spawn((
+        Text::default(),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        },
+        HudOverlay,
+        OnGameScreen,
+    ));
+
+    // hidden until a boss comes out
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                left: Val::Percent(25.0),
+                width: Val::Percent(50.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            Visibility::Hidden,
+            BossHealthBar,
+            OnGameScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((Text::default(), BossHealthText));
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(20.0),
+                        border: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+                    BorderColor(Color::BLACK),
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Node {
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.8, 0.1, 0.1)),
+                        BossHealthFill,
+                    ));
+                });
+        });
+}
+
+fn display_hud(
+    all_assets: Res<AllAssets>,
+    assets_waves: Res<Assets<WaveDetails>>,
+    assets_enemies: Res<Assets<EnemyDetails>>,
+    assets_elements: Res<Assets<ElementChart>>,
+    wave_counter: Res<WaveCounter>,
+    lives: Res<Lives>,
+    mut query: Query<&mut Text, With<HudOverlay>>,
+) {
+    let chart = assets_elements.get(&all_assets.elements).unwrap();
+
+    // the elemental matchups of each enemy in the upcoming (or current) wave
+    let mut enemies = Vec::new();
+    if let Some(wave) = all_assets
+        .waves
+        .get(wave_counter.current)
+        .and_then(|handle| assets_waves.get(handle))
+    {
+        let bosses = wave.boss.iter().map(|boss| &boss.enemy);
+        for enemy in wave.groups.iter().map(|group| &group.enemy).chain(bosses) {
+            if enemies.contains(enemy) {
+                continue;
+            }
+            enemies.push(enemy.clone());
+        }
+    }
+    let matchups = enemies
+        .iter()
+        .filter_map(|name| assets_enemies.iter().find(|(_, enemy)| &enemy.name == name))
+        .map(|(_, enemy)| {
+            let elements_where = |effective: fn(f32) -> bool| {
+                BaseElementType::ALL
+                    .iter()
+                    .filter(|attacker| effective(chart.multiplier(attacker, &enemy.element_type)))
+                    .map(|attacker| attacker.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            };
+            format!(
+                "{} ({})\n  Weak to: {}\n  Resists: {}",
+                enemy.name,
+                enemy.element_type,
+                elements_where(|multiplier| multiplier > 1.0),
+                elements_where(|multiplier| multiplier < 1.0),
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    for mut text in query.iter_mut() {
+        text.0 = format!(
+            "Wave: {}/{}\nLives: {}\n\n{}",
+            (wave_counter.current + 1).min(all_assets.waves.len()),
+            all_assets.waves.len(),
+            lives.0,
+            matchups
+        );
+    }
+}
+
+fn display_boss_health(
+    bosses: Query<(&Enemy, Option<&EnemyPhases>), With<Boss>>,
+    mut bar: Query<&mut Visibility, With<BossHealthBar>>,
+    mut fill: Query<&mut Node, With<BossHealthFill>>,
+    mut text: Query<&mut Text, With<BossHealthText>>,
+) {
+    let Ok(mut visibility) = bar.get_single_mut() else {
+        return;
+    };
+    let Some((boss, phases)) = bosses.iter().next() else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    *visibility = Visibility::Inherited;
+    for mut node in fill.iter_mut() {
+        node.width = Val::Percent(boss.health_fraction() * 100.0);
+    }
+    for mut text in text.iter_mut() {
+        text.0 = match phases {
+            Some(phases) => format!(
+                "{} (phase {}/{})",
+                boss.name(),
+                phases.current(),
+                phases.count()
+            ),
+            None => boss.name().to_string(),
+        };
+    }
+}
@@ -0,0 +1,232 @@
+use bevy::prelude::*;
+
+use crate::{settings::Settings, GameState};
+
+use super::{
+    wave::{Enemy, EnemyDamagedEvent, EnemyModel},
+    OnGameScreen,
+};
+
+pub struct IndicatorsPlugin;
+
+impl Plugin for IndicatorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Game), setup)
+            .add_systems(
+                Update,
+                (
+                    add_health_bars,
+                    update_health_bars,
+                    spawn_damage_numbers,
+                    float_damage_numbers,
+                )
+                    .run_if(in_state(GameState::Game)),
+            );
+    }
+}
+
+// height of the health bar above the enemy's model, in the model's own units
+const HEALTH_BAR_HEIGHT: f32 = 2.4;
+const HEALTH_BAR_THICKNESS: f32 = 0.12;
+
+// how long damage numbers float for, and how fast they rise
+const DAMAGE_NUMBER_SECONDS: f32 = 0.8;
+const DAMAGE_NUMBER_RISE: f32 = 1.0;
+
+#[derive(Resource)]
+struct IndicatorAssets {
+    bar_mesh: Handle<Mesh>,
+    background: Handle<StandardMaterial>,
+    fill: Handle<StandardMaterial>,
+}
+
+/// Bar floating above an enemy's model, always turned towards the camera.
+#[derive(Component)]
+struct HealthBar {
+    // width of the bar at full health, wider for enemies with more health
+    width: f32,
+}
+
+#[derive(Component)]
+struct HealthBarFill;
+
+/// Damage dealt to an enemy, floating up from where it was hit.
+#[derive(Component)]
+struct DamageNumber {
+    position: Vec3,
+    timer: Timer,
+}
+
+fn setup(
+    mut commands: Commands,
+    mut assets_mesh: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.insert_resource(IndicatorAssets {
+        bar_mesh: assets_mesh.add(Rectangle::new(1.0, 1.0)),
+        background: materials.add(StandardMaterial {
+            base_color: Color::srgb(0.1, 0.1, 0.1),
+            unlit: true,
+            ..default()
+        }),
+        fill: materials.add(StandardMaterial {
+            base_color: Color::srgb(0.1, 0.8, 0.1),
+            unlit: true,
+            ..default()
+        }),
+    });
+}
+
+// width of a health bar for an enemy with the given max health, so tougher enemies stand out
+fn health_bar_width(max_health: u32) -> f32 {
+    ((max_health as f32).sqrt() * 0.4).clamp(0.8, 4.0)
+}
+
+// hangs a health bar off every new enemy model, including those swapped in by phases
+fn add_health_bars(
+    mut commands: Commands,
+    indicator_assets: Res<IndicatorAssets>,
+    models: Query<(Entity, &Parent), Added<EnemyModel>>,
+    enemies: Query<&Enemy>,
+) {
+    for (model, parent) in models.iter() {
+        let Ok(enemy) = enemies.get(parent.get()) else {
+            continue;
+        };
+        let width = health_bar_width(enemy.max_health());
+        commands.entity(model).with_children(|parent| {
+            parent
+                .spawn((
+                    Transform::from_translation(Vec3::Y * HEALTH_BAR_HEIGHT),
+                    Visibility::default(),
+                    HealthBar { width },
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Mesh3d(indicator_assets.bar_mesh.clone()),
+                        MeshMaterial3d(indicator_assets.background.clone()),
+                        Transform::from_scale(Vec3::new(width, HEALTH_BAR_THICKNESS, 1.0)),
+                    ));
+                    // slightly in front of the background
+                    parent.spawn((
+                        Mesh3d(indicator_assets.bar_mesh.clone()),
+                        MeshMaterial3d(indicator_assets.fill.clone()),
+                        Transform::from_xyz(0.0, 0.0, 0.001).with_scale(Vec3::new(
+                            width,
+                            HEALTH_BAR_THICKNESS,
+                            1.0,
+                        )),
+                        HealthBarFill,
+                    ));
+                });
+        });
+    }
+}
+
+// turns health bars towards the camera and shrinks their fill as enemies are hurt
+fn update_health_bars(
+    settings: Res<Settings>,
+    camera: Query<&GlobalTransform, With<Camera3d>>,
+    enemies: Query<&Enemy>,
+    models: Query<(&Parent, &GlobalTransform), Without<HealthBar>>,
+    mut bars: Query<(
+        &HealthBar,
+        &Parent,
+        &Children,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+    mut fills: Query<&mut Transform, (With<HealthBarFill>, Without<HealthBar>)>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    for (bar, model, children, mut transform, mut visibility) in bars.iter_mut() {
+        let Ok((enemy_entity, model_transform)) = models.get(model.get()) else {
+            continue;
+        };
+        let Ok(enemy) = enemies.get(enemy_entity.get()) else {
+            continue;
+        };
+        *visibility = if settings.show_health_bars {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        // undo the model's own turning so the bar faces the camera
+        transform.rotation = model_transform.rotation().inverse() * camera.rotation();
+
+        let fraction = enemy.health_fraction();
+        for &child in children.iter() {
+            if let Ok(mut fill) = fills.get_mut(child) {
+                // shrink towards the left hand side of the bar
+                fill.scale.x = bar.width * fraction;
+                fill.translation.x = -bar.width * (1.0 - fraction) / 2.0;
+            }
+        }
+    }
+}
+
+fn spawn_damage_numbers(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut ev_damaged: EventReader<EnemyDamagedEvent>,
+) {
+    for ev in ev_damaged.read() {
+        if !settings.show_damage_numbers {
+            continue;
+        }
+        commands.spawn((
+            Text::new(ev.damage.to_string()),
+            TextFont {
+                font_size: 18.0,
+                ..default()
+            },
+            TextColor(Color::WHITE),
+            Node {
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            // placed over the enemy once it's been projected onto the screen
+            Visibility::Hidden,
+            DamageNumber {
+                position: ev.position + Vec3::Y,
+                timer: Timer::from_seconds(DAMAGE_NUMBER_SECONDS, TimerMode::Once),
+            },
+            OnGameScreen,
+        ));
+    }
+}
+
+// floats damage numbers upwards, fading them out as they go
+fn float_damage_numbers(
+    mut commands: Commands,
+    time: Res<Time>,
+    camera: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut numbers: Query<(
+        Entity,
+        &mut DamageNumber,
+        &mut Node,
+        &mut TextColor,
+        &mut Visibility,
+    )>,
+) {
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+    for (entity, mut number, mut node, mut color, mut visibility) in numbers.iter_mut() {
+        if number.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        number.position.y += DAMAGE_NUMBER_RISE * time.delta_secs();
+        let Ok(screen) = camera.world_to_viewport(camera_transform, number.position) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        *visibility = Visibility::Inherited;
+        node.left = Val::Px(screen.x);
+        node.top = Val::Px(screen.y);
+        color.0.set_alpha(number.timer.fraction_remaining());
+    }
+}
@@ -0,0 +1,237 @@
+use bevy::{gltf::GltfMesh, prelude::*, render::primitives::Aabb};
+use bevy_asset_loader::prelude::*;
+use bevy_common_assets::ron::RonAssetPlugin;
+use vleue_navigator::prelude::*;
+
+use crate::GameState;
+
+use super::{
+    grid::{cell_at, cell_center, Grid},
+    wave::EnemySpawner,
+    EnemyDetails, Goal, Obstacle, OnGameScreen,
+};
+
+pub struct MapPlugin;
+
+impl Plugin for MapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<MapDetails>::new(&["map.ron"]))
+            .add_systems(OnEnter(GameState::Game), spawn_map);
+    }
+}
+
+/// Every map that can be picked from the level select.
+///
+/// Maps are listed one by one, as folders can't be loaded on the web.
+#[derive(AssetCollection, Resource)]
+pub struct MapAssets {
+    #[asset(
+        paths("maps/meadow.map.ron", "maps/crossroads.map.ron"),
+        collection(typed)
+    )]
+    pub maps: Vec<Handle<MapDetails>>,
+}
+
+/// The map picked in the level select, played until another is picked.
+#[derive(Resource, Debug, Clone)]
+pub struct SelectedMap(pub Handle<MapDetails>);
+
+/// Representation of a loaded `*.map.ron` file, positions are in world x/z.
+#[derive(serde::Deserialize, Asset, Debug, TypePath, Clone)]
+pub struct MapDetails {
+    pub name: String,
+    /// Opposite corners of the grid towers are built on
+    pub bounds: ((f32, f32), (f32, f32)),
+    /// Outer edges of the area enemies can walk in, the bounds if not set
+    #[serde(default)]
+    pub boundary: Vec<(f32, f32)>,
+    /// Where enemies come from, in the order waves refer to them
+    pub spawners: Vec<SpawnerRon>,
+    /// Where enemies are heading
+    pub goals: Vec<(f32, f32)>,
+    /// Static obstacles, such as the house
+    #[serde(default)]
+    pub props: Vec<PropRon>,
+    /// Opposite corners of areas towers can't be built in, though enemies can walk there
+    #[serde(default)]
+    pub unbuildable: Vec<((f32, f32), (f32, f32))>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct SpawnerRon {
+    pub position: (f32, f32),
+    /// Index of the goal enemies from this spawner head for, the nearest one if not set
+    #[serde(default)]
+    pub goal: Option<usize>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct PropRon {
+    pub name: String,
+    pub model: String,
+    pub position: (f32, f32),
+    /// Degrees turned around the vertical axis
+    #[serde(default)]
+    pub rotation: f32,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    /// Width and depth of the area the prop blocks, centred on its position
+    pub size: (f32, f32),
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+impl MapDetails {
+    /// Outer edges of the area enemies can walk in, in world x/z.
+    pub fn boundary(&self) -> Vec<Vec2> {
+        if self.boundary.is_empty() {
+            let ((min_x, min_z), (max_x, max_z)) = self.bounds;
+            return vec![
+                Vec2::new(min_x, min_z),
+                Vec2::new(max_x, min_z),
+                Vec2::new(max_x, max_z),
+                Vec2::new(min_x, max_z),
+            ];
+        }
+        self.boundary
+            .iter()
+            .map(|(x, z)| Vec2::new(*x, *z))
+            .collect()
+    }
+
+    /// Outer edges of the navmesh, in its own 2d space.
+    pub fn navmesh_edges(&self) -> Vec<Vec2> {
+        // the navmesh's y axis points along the world's -z axis
+        self.boundary()
+            .iter()
+            .map(|edge| Vec2::new(edge.x, -edge.y))
+            .collect()
+    }
+}
+
+// whether a point is inside a polygon, by counting how many of its edges a ray from the
+// point crosses
+fn polygon_contains(polygon: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn spawn_map(
+    mut commands: Commands,
+    selected_map: Res<SelectedMap>,
+    assets_maps: Res<Assets<MapDetails>>,
+    asset_server: Res<AssetServer>,
+    assets_gltfmesh: Res<Assets<GltfMesh>>,
+    mut assets_mesh: ResMut<Assets<Mesh>>,
+    assets_enemydetails: Res<Assets<EnemyDetails>>,
+    res: Res<Assets<Gltf>>,
+) {
+    let map = assets_maps.get(&selected_map.0).unwrap();
+    info!("Loading map: {}", map.name);
+
+    let ((min_x, min_z), (max_x, max_z)) = map.bounds;
+    let mut grid = Grid::new(Vec2::new(min_x, min_z), Vec2::new(max_x, max_z));
+    for ((min_x, min_z), (max_x, max_z)) in map.unbuildable.iter() {
+        grid.set_unbuildable_area(Vec2::new(*min_x, *min_z), Vec2::new(*max_x, *max_z));
+    }
+    // towers can only be built where enemies can walk
+    let boundary = map.boundary();
+    for cell in grid.cells().collect::<Vec<IVec2>>() {
+        if !polygon_contains(&boundary, cell_center(cell).xz()) {
+            grid.set_unbuildable(cell);
+        }
+    }
+    // nor on top of anything the map places
+    let spawner_positions = map.spawners.iter().map(|spawner| &spawner.position);
+    for (x, z) in spawner_positions.chain(map.goals.iter()) {
+        grid.set_unbuildable(cell_at(Vec3::new(*x, 0.0, *z)));
+    }
+    for prop in map.props.iter() {
+        let (x, z) = prop.position;
+        let half_size = Vec2::new(prop.size.0, prop.size.1) / 2.0;
+        grid.set_unbuildable_area(Vec2::new(x, z) - half_size, Vec2::new(x, z) + half_size);
+    }
+    commands.insert_resource(grid);
+
+    // spawners look like the first enemy
+    let enemy = assets_enemydetails.iter().next().unwrap();
+    let enemy_mesh = res.get(&enemy.1.model).unwrap();
+    let enemy_mesh_mesh = assets_gltfmesh.get(&enemy_mesh.meshes[0]).unwrap();
+
+    for (id, spawner) in map.spawners.iter().enumerate() {
+        let (x, z) = spawner.position;
+        commands.spawn((
+            Mesh3d(enemy_mesh_mesh.primitives[0].mesh.clone()),
+            MeshMaterial3d(enemy_mesh.materials[0].clone()),
+            Transform::from_translation(Vec3::new(x, 0.0, z)),
+            EnemySpawner {
+                id,
+                goal: spawner.goal,
+            },
+            OnGameScreen,
+        ));
+    }
+
+    // spawn square placeholders for goals
+    let goal_mesh = assets_mesh.add(Rectangle::new(0.1, 1.0));
+    for (id, (x, z)) in map.goals.iter().enumerate() {
+        commands.spawn((
+            Mesh3d(goal_mesh.clone()),
+            Transform::default()
+                .with_translation(Vec3::new(*x, 0.0, *z))
+                .with_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)),
+            Goal { id },
+            OnGameScreen,
+        ));
+    }
+
+    for prop in map.props.iter() {
+        let (x, z) = prop.position;
+        let (width, depth) = prop.size;
+        let rotation = Quat::from_rotation_y(prop.rotation.to_radians());
+        // the prop's area is its own obstacle, so it doesn't depend on the model's bounds
+        commands
+            .spawn((
+                Transform::from_translation(Vec3::new(x, 0.0, z)),
+                Visibility::default(),
+                Aabb::from_min_max(
+                    Vec3::new(-width / 2.0, 0.0, -depth / 2.0),
+                    Vec3::new(width / 2.0, 1.0, depth / 2.0),
+                ),
+                Obstacle,
+                Name::new(prop.name.clone()),
+                OnGameScreen,
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    SceneRoot(
+                        asset_server.load(GltfAssetLabel::Scene(0).from_asset(prop.model.clone())),
+                    ),
+                    Transform::from_rotation(rotation).with_scale(Vec3::splat(prop.scale)),
+                ));
+            });
+    }
+
+    commands.spawn((
+        NavMeshSettings {
+            // Define the outer borders of the navmesh.
+            fixed: Triangulation::from_outer_edges(&map.navmesh_edges()),
+            ..default()
+        },
+        // Mark it for update as soon as obstacles are changed.
+        // Other modes can be debounced or manually triggered.
+        NavMeshUpdateMode::Direct,
+        Transform::from_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)),
+        OnGameScreen,
+    ));
+}
@@ -1,9 +1,21 @@
-use bevy::{gltf::GltfMesh, prelude::*};
+use std::time::Duration;
+
+use bevy::{gltf::GltfMesh, prelude::*, render::primitives::Aabb, utils::HashMap};
 use leafwing_input_manager::{prelude::*, Actionlike, InputControlKind};
+use vleue_navigator::prelude::*;
 
 use crate::{despawn_screen, GameState};
 
-use super::{BaseElementType, GamePlayState, Obstacle, TowerDetails, TowerPool, Wave, SNAP_OFFSET};
+use super::{
+    economy::Economy,
+    footprint::{face_bonuses, Footprint, FootprintCell},
+    grid::{cell_at, cell_center, Grid},
+    map::{MapDetails, SelectedMap},
+    status::StatusEffect,
+    wave::{route, EnemySpawner},
+    BaseElementType, GamePlayState, Goal, Obstacle, OnGameScreen, ProjectileKind, Rarity,
+    TargetingMode, TowerDetails, TowerPool, TowerUpgrade, SNAP_OFFSET,
+};
 
 pub struct PlacementPlugin;
 
@@ -19,16 +31,47 @@ impl Plugin for PlacementPlugin {
                     control_cursor,
                     placeholder_snap_to_cursor,
                     display_placeholder,
+                    toggle_selection,
+                    merge_towers,
+                    draw_grid,
+                    draw_paths,
+                    start_wave,
+                )
+                    .run_if(in_state(GameState::Game).and(in_state(GamePlayState::Placement))),
+            )
+            .add_systems(
+                Update,
+                (
                     toggle_placeholder_type,
+                    cycle_targeting,
+                    transform_footprint,
+                    check_placement,
+                    display_placement_check,
+                    draw_footprint,
                     place_tower,
                     display_tower_pool,
-                    start_wave,
                 )
-                    .run_if(in_state(GameState::Game).and(in_state(GamePlayState::Placement))),
+                    .run_if(
+                        in_state(GameState::Game)
+                            .and(in_state(GamePlayState::Placement))
+                            .and(resource_equals(PlacementMode::Placing)),
+                    ),
+            )
+            .add_systems(
+                Update,
+                (sell_tower, upgrade_tower, draw_selection, display_selection).run_if(
+                    in_state(GameState::Game)
+                        .and(in_state(GamePlayState::Placement))
+                        .and(resource_equals(PlacementMode::Selecting)),
+                ),
             )
             .add_systems(
                 OnExit(GamePlayState::Placement),
-                despawn_screen::<OnPlacementOverlay>,
+                (
+                    despawn_screen::<OnPlacementOverlay>,
+                    despawn_screen::<TowerPlaceholder>,
+                    despawn_screen::<CursorPlaceholder>,
+                ),
             );
     }
 }
@@ -37,7 +80,14 @@ impl Plugin for PlacementPlugin {
 enum PlacementAction {
     MoveCursorPlaceholder,
     ToggleTowerType,
+    CycleTargeting,
+    RotateFootprint,
+    MirrorFootprint,
     PlaceTower,
+    ToggleSelection,
+    SellTower,
+    UpgradeTower,
+    MergeTowers,
     EndPlacement,
 }
 
@@ -46,7 +96,14 @@ impl Actionlike for PlacementAction {
         match self {
             PlacementAction::MoveCursorPlaceholder => InputControlKind::DualAxis,
             PlacementAction::ToggleTowerType => InputControlKind::Button,
+            PlacementAction::CycleTargeting => InputControlKind::Button,
+            PlacementAction::RotateFootprint => InputControlKind::Button,
+            PlacementAction::MirrorFootprint => InputControlKind::Button,
             PlacementAction::PlaceTower => InputControlKind::Button,
+            PlacementAction::ToggleSelection => InputControlKind::Button,
+            PlacementAction::SellTower => InputControlKind::Button,
+            PlacementAction::UpgradeTower => InputControlKind::Button,
+            PlacementAction::MergeTowers => InputControlKind::Button,
             PlacementAction::EndPlacement => InputControlKind::Button,
         }
     }
@@ -60,13 +117,27 @@ impl PlacementAction {
         // Default gamepad input bindings
         input_map.insert_dual_axis(Self::MoveCursorPlaceholder, GamepadStick::RIGHT);
         input_map.insert(Self::ToggleTowerType, GamepadButton::East);
+        input_map.insert(Self::CycleTargeting, GamepadButton::North);
+        input_map.insert(Self::RotateFootprint, GamepadButton::RightTrigger);
+        input_map.insert(Self::MirrorFootprint, GamepadButton::LeftTrigger);
         input_map.insert(Self::PlaceTower, GamepadButton::South);
+        input_map.insert(Self::ToggleSelection, GamepadButton::Select);
+        input_map.insert(Self::SellTower, GamepadButton::DPadDown);
+        input_map.insert(Self::UpgradeTower, GamepadButton::DPadUp);
+        input_map.insert(Self::MergeTowers, GamepadButton::RightThumb);
         input_map.insert(Self::EndPlacement, GamepadButton::West);
 
         // // Default kbm input bindings
         input_map.insert_dual_axis(Self::MoveCursorPlaceholder, VirtualDPad::arrow_keys());
         input_map.insert(Self::ToggleTowerType, KeyCode::KeyT);
+        input_map.insert(Self::CycleTargeting, KeyCode::KeyR);
+        input_map.insert(Self::RotateFootprint, KeyCode::KeyF);
+        input_map.insert(Self::MirrorFootprint, KeyCode::KeyG);
         input_map.insert(Self::PlaceTower, KeyCode::Space);
+        input_map.insert(Self::ToggleSelection, KeyCode::KeyE);
+        input_map.insert(Self::SellTower, KeyCode::KeyX);
+        input_map.insert(Self::UpgradeTower, KeyCode::KeyU);
+        input_map.insert(Self::MergeTowers, KeyCode::KeyM);
         input_map.insert(Self::EndPlacement, KeyCode::Enter);
 
         input_map
@@ -79,15 +150,71 @@ pub struct Tower {
     pub name: String,
     pub element_type: BaseElementType,
     pub attack_speed: Timer,
+    pub range: f32,
+    pub damage: u32,
+    pub projectile_speed: f32,
+    pub projectile_model: Option<Handle<Gltf>>,
+    pub projectile: ProjectileKind,
+    pub projectile_color: Color,
+    pub projectile_size: f32,
+    pub effects: Vec<StatusEffect>,
+    pub can_target_ground: bool,
+    pub can_target_air: bool,
+    // the details the tower was built from, which list its upgrades
+    pub details: AssetId<TowerDetails>,
+    // number of upgrades bought so far
+    pub tier: usize,
+    // money spent on the tower, from the die it was rolled from and its upgrades
+    pub value: usize,
+    // rarity of the face it was rolled on, raised by merging
+    pub rarity: Rarity,
+    // damage the faces under its footprint add, which its rarity multiplies along with the base
+    pub bonus_damage: u32,
 }
 
-#[derive(Reflect, Component)]
-#[reflect(Component)]
-pub struct Projectile {
-    pub speed: f32,
-    pub damage: u32,
-    pub target: Entity,
-    pub lifetime: Timer,
+// percentage of what a tower is worth paid back when it's sold
+const SELL_REFUND_PERCENT: usize = 50;
+
+// number of towers of the same kind and rarity merged into one of the next rarity
+const MERGE_COUNT: usize = 3;
+
+impl Tower {
+    pub fn sell_value(&self) -> usize {
+        self.value * SELL_REFUND_PERCENT / 100
+    }
+
+    fn upgrade(&mut self, upgrade: &TowerUpgrade) {
+        self.damage += upgrade.damage;
+        self.range += upgrade.range;
+        if let Some(attack_interval) = upgrade.attack_interval {
+            self.attack_speed
+                .set_duration(Duration::from_secs_f32(attack_interval));
+        }
+        self.tier += 1;
+        self.value += upgrade.cost;
+    }
+
+    // raises the tower to the given rarity, working its stats out again from its details and
+    // the upgrades bought so far, whose damage the rarity doesn't multiply
+    fn promote(&mut self, rarity: Rarity, details: &TowerDetails) {
+        let upgrades = &details.upgrades[..self.tier];
+        self.damage = ((details.damage + self.bonus_damage) as f32 * rarity.damage_multiplier())
+            .round() as u32
+            + upgrades.iter().map(|upgrade| upgrade.damage).sum::<u32>();
+        self.range = details.range
+            + rarity.range_bonus()
+            + upgrades.iter().map(|upgrade| upgrade.range).sum::<f32>();
+        self.rarity = rarity;
+    }
+}
+
+/// Whether the cursor is placing towers from the pool, or picking placed towers to sell
+/// or upgrade.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+enum PlacementMode {
+    #[default]
+    Placing,
+    Selecting,
 }
 
 #[derive(Reflect, Component, Default)]
@@ -101,6 +228,38 @@ pub struct CursorPlaceholder;
 #[derive(Reflect, Component)]
 pub struct OnPlacementOverlay;
 
+/// Result of checking the tower placeholder's current spot against the navmesh.
+#[derive(Resource, Default)]
+struct PlacementCheck {
+    // snapped position of the placeholder that was checked
+    position: Option<Vec3>,
+    // footprint of the placeholder that was checked
+    footprint: Option<Footprint>,
+    // cells covered by the footprint at that position
+    cells: Vec<IVec2>,
+    // the footprint covers a cell that's off the grid, unbuildable or taken by another tower
+    overlapping: bool,
+    // placing a tower there would cut a spawner off from the goal
+    blocked: bool,
+    // the paths enemies would take with a tower placed there
+    paths: Vec<Vec<Vec3>>,
+}
+
+impl PlacementCheck {
+    fn is_valid(&self) -> bool {
+        !self.overlapping && !self.blocked
+    }
+}
+
+#[derive(Resource)]
+struct PlacementAssets {
+    valid: Handle<StandardMaterial>,
+    blocked: Handle<StandardMaterial>,
+    // ground tile placed under each cell of a tower's footprint, coloured by the cell's element
+    tile_mesh: Handle<Mesh>,
+    tile_materials: HashMap<BaseElementType, Handle<StandardMaterial>>,
+}
+
 fn setup(
     mut commands: Commands,
     current_tower: ResMut<TowerPool>,
@@ -110,31 +269,55 @@ fn setup(
     res: Res<Assets<Gltf>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    // pick first tower in the list
-    // will panic, need to address in situation where there are no towers
-    let tower = current_tower.towers[current_tower.highlighted];
-    let tower_details = &assets_towers.get(tower).unwrap().model;
-    let gltf = res.get(tower_details).unwrap();
-    let mesh = assets_gltfmesh.get(&gltf.meshes[0]).unwrap();
-    let mesh3d = mesh.primitives[0].mesh.clone();
-    let mat = gltf.materials[0].clone();
+    // the placeholder shows the highlighted tower, if there are any left to place
+    if let Some(tower) = current_tower.towers.get(current_tower.highlighted) {
+        let tower_details = assets_towers.get(tower.details).unwrap();
+        let gltf = res.get(&tower_details.model).unwrap();
+        let mesh = assets_gltfmesh.get(&gltf.meshes[0]).unwrap();
+        let mesh3d = mesh.primitives[0].mesh.clone();
+        let mat = gltf.materials[0].clone();
+        commands.spawn((
+            Mesh3d(mesh3d),
+            MeshMaterial3d(mat),
+            Transform::default().with_translation(Vec3::new(SNAP_OFFSET, 0.0, SNAP_OFFSET)),
+            TowerPlaceholder,
+            tower_details.targeting,
+            Footprint::new(tower.net),
+        ));
+    }
+
     let pink = materials.add(StandardMaterial {
         base_color: Color::srgb(1.0, 0.0, 1.0),
         ..Default::default()
     });
-    commands.spawn((
-        Mesh3d(mesh3d),
-        MeshMaterial3d(mat),
-        Transform::default().with_translation(Vec3::new(SNAP_OFFSET, 0.0, SNAP_OFFSET)),
-        TowerPlaceholder,
-    ));
+    let red = materials.add(StandardMaterial {
+        base_color: Color::srgb(1.0, 0.0, 0.0),
+        ..Default::default()
+    });
 
     commands.spawn((
         Mesh3d(assets_mesh.add(Cylinder::new(0.5, 0.2))),
-        MeshMaterial3d(pink),
+        MeshMaterial3d(pink.clone()),
         Transform::default().with_translation(Vec3::new(SNAP_OFFSET, 0.0, SNAP_OFFSET)),
         CursorPlaceholder,
     ));
+    commands.insert_resource(PlacementAssets {
+        valid: pink,
+        blocked: red,
+        tile_mesh: assets_mesh.add(Cuboid::new(1.0, 0.05, 1.0)),
+        tile_materials: BaseElementType::ALL
+            .iter()
+            .map(|element| {
+                let material = materials.add(StandardMaterial {
+                    base_color: element.color(),
+                    ..Default::default()
+                });
+                (element.clone(), material)
+            })
+            .collect(),
+    });
+    commands.insert_resource(PlacementCheck::default());
+    commands.insert_resource(PlacementMode::default());
 
     commands.spawn((Text::default(), OnPlacementOverlay));
 }
@@ -153,8 +336,9 @@ fn control_cursor(
     player_transform.translation += Vec3::new(move_delta.x, 0.0, -move_delta.y);
 }
 
-// snaps the tower placeholder to the nearest spot on the grid to the cursor
+// snaps the tower placeholder to the centre of the grid cell under the cursor
 fn placeholder_snap_to_cursor(
+    grid: Res<Grid>,
     mut placeholder_query: Query<
         &mut Transform,
         (With<TowerPlaceholder>, Without<CursorPlaceholder>),
@@ -162,26 +346,66 @@ fn placeholder_snap_to_cursor(
     cursor_query: Query<&mut Transform, (With<CursorPlaceholder>, Without<TowerPlaceholder>)>,
 ) {
     let cursor_transform = cursor_query.single();
-    let cursor_position = cursor_transform.translation;
+    let snapped = cell_center(grid.clamp(cell_at(cursor_transform.translation)));
 
     placeholder_query
         .iter_mut()
         .for_each(|mut placeholder_transform| {
-            let snap_distance = 1.0;
-            let snap_x = (cursor_position.x - SNAP_OFFSET / snap_distance).round() + SNAP_OFFSET;
-            let snap_z = (cursor_position.z - SNAP_OFFSET / snap_distance).round() + SNAP_OFFSET;
-
-            placeholder_transform.translation.x = snap_x;
-            placeholder_transform.translation.z = snap_z;
+            placeholder_transform.translation.x = snapped.x;
+            placeholder_transform.translation.z = snapped.z;
         });
 }
 
 fn toggle_placeholder_type(
     action_state: Res<ActionState<PlacementAction>>,
     mut tower_pool: ResMut<TowerPool>,
+    assets_towers: Res<Assets<TowerDetails>>,
+    mut query: Query<(&mut TargetingMode, &mut Footprint), With<TowerPlaceholder>>,
 ) {
     if action_state.just_pressed(&PlacementAction::ToggleTowerType) {
         tower_pool.toggle_highlighted();
+        reset_placeholder(&tower_pool, &assets_towers, &mut query);
+    }
+}
+
+fn cycle_targeting(
+    action_state: Res<ActionState<PlacementAction>>,
+    mut query: Query<&mut TargetingMode, With<TowerPlaceholder>>,
+) {
+    if action_state.just_pressed(&PlacementAction::CycleTargeting) {
+        for mut targeting in query.iter_mut() {
+            *targeting = targeting.next();
+        }
+    }
+}
+
+fn transform_footprint(
+    action_state: Res<ActionState<PlacementAction>>,
+    mut query: Query<&mut Footprint, With<TowerPlaceholder>>,
+) {
+    for mut footprint in query.iter_mut() {
+        if action_state.just_pressed(&PlacementAction::RotateFootprint) {
+            footprint.rotate();
+        }
+        if action_state.just_pressed(&PlacementAction::MirrorFootprint) {
+            footprint.mirror();
+        }
+    }
+}
+
+// sets the placeholder's targeting and footprint back to the defaults of the highlighted tower
+fn reset_placeholder(
+    tower_pool: &TowerPool,
+    assets_towers: &Assets<TowerDetails>,
+    query: &mut Query<(&mut TargetingMode, &mut Footprint), With<TowerPlaceholder>>,
+) {
+    let Some(tower) = tower_pool.towers.get(tower_pool.highlighted) else {
+        return;
+    };
+    let tower_details = assets_towers.get(tower.details).unwrap();
+    for (mut targeting, mut footprint) in query.iter_mut() {
+        *targeting = tower_details.targeting;
+        *footprint = Footprint::new(tower.net);
     }
 }
 
@@ -202,8 +426,8 @@ fn display_placeholder(
         });
         return;
     }
-    let tower = tower_pool.towers[tower_pool.highlighted];
-    let tower_details = &assets_towers.get(tower).unwrap().model;
+    let tower = &tower_pool.towers[tower_pool.highlighted];
+    let tower_details = &assets_towers.get(tower.details).unwrap().model;
     let gltf = res.get(tower_details).unwrap();
     let mesh = assets_gltfmesh.get(&gltf.meshes[0]).unwrap();
     query.iter_mut().for_each(|(mut mesh3d, mut mat, _)| {
@@ -212,6 +436,208 @@ fn display_placeholder(
     });
 }
 
+// checks whether the placeholder's footprint fits on free cells of the grid, and whether
+// placing the tower there would block any enemy's path, by building a navmesh with the
+// footprint's cells as extra obstacles
+fn check_placement(
+    mut check: ResMut<PlacementCheck>,
+    grid: Res<Grid>,
+    selected_map: Res<SelectedMap>,
+    assets_maps: Res<Assets<MapDetails>>,
+    placeholder_query: Query<(&Transform, &Footprint), With<TowerPlaceholder>>,
+    obstacles: Query<(&Aabb, &GlobalTransform), With<Obstacle>>,
+    added_obstacles: Query<(), Added<Obstacle>>,
+    navmesh_query: Query<&Transform, With<NavMeshSettings>>,
+    spawners: Query<(&EnemySpawner, &Transform)>,
+    goals: Query<(&Goal, &Transform)>,
+) {
+    let Ok((placeholder_transform, footprint)) = placeholder_query.get_single() else {
+        *check = PlacementCheck::default();
+        return;
+    };
+    if check.position == Some(placeholder_transform.translation)
+        && check.footprint == Some(*footprint)
+        && added_obstacles.is_empty()
+        && !grid.is_changed()
+    {
+        return;
+    }
+    let navmesh_transform = navmesh_query.single();
+
+    let origin = cell_at(placeholder_transform.translation);
+    let cells = footprint.cells().map(|cell| origin + cell).to_vec();
+    let cell_aabb = Aabb::from_min_max(Vec3::new(-0.5, 0.0, -0.5), Vec3::new(0.5, 0.05, 0.5));
+
+    check.overlapping = cells.iter().any(|cell| !grid.is_buildable(*cell));
+
+    let mut polygons = obstacles
+        .iter()
+        .map(|(aabb, transform)| obstacle_polygon(aabb, transform, navmesh_transform))
+        .collect::<Vec<Vec<Vec2>>>();
+    polygons.extend(cells.iter().map(|cell| {
+        obstacle_polygon(
+            &cell_aabb,
+            &GlobalTransform::from_translation(cell_center(*cell)),
+            navmesh_transform,
+        )
+    }));
+    let map = assets_maps.get(&selected_map.0).unwrap();
+    let mut navmesh = NavMesh::from_edge_and_obstacles(map.navmesh_edges(), polygons);
+    navmesh.set_transform(*navmesh_transform);
+
+    check.position = Some(placeholder_transform.translation);
+    check.footprint = Some(*footprint);
+    check.cells = cells;
+    check.blocked = false;
+    check.paths.clear();
+    for (spawner, transform) in spawners.iter() {
+        let goals = goals.iter().map(|(goal, to)| (goal.id, to.translation));
+        match route(&navmesh, transform.translation, spawner.goal, goals) {
+            Some(path) => check
+                .paths
+                .push([vec![transform.translation], path].concat()),
+            None => check.blocked = true,
+        }
+    }
+}
+
+// the rectangle an obstacle covers on the ground, in the navmesh's 2d space
+fn obstacle_polygon(
+    aabb: &Aabb,
+    transform: &GlobalTransform,
+    navmesh_transform: &Transform,
+) -> Vec<Vec2> {
+    let to_navmesh = navmesh_transform.compute_affine().inverse();
+    let (min, max) = (Vec3::from(aabb.min()), Vec3::from(aabb.max()));
+    let corners = [
+        Vec3::new(min.x, min.y, min.z),
+        Vec3::new(max.x, min.y, min.z),
+        Vec3::new(min.x, max.y, min.z),
+        Vec3::new(max.x, max.y, min.z),
+        Vec3::new(min.x, min.y, max.z),
+        Vec3::new(max.x, min.y, max.z),
+        Vec3::new(min.x, max.y, max.z),
+        Vec3::new(max.x, max.y, max.z),
+    ]
+    .map(|corner| {
+        to_navmesh
+            .transform_point3(transform.transform_point(corner))
+            .xy()
+    });
+    let min = corners
+        .iter()
+        .fold(Vec2::MAX, |acc, corner| acc.min(*corner));
+    let max = corners
+        .iter()
+        .fold(Vec2::MIN, |acc, corner| acc.max(*corner));
+    vec![min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)]
+}
+
+// turns the cursor red while the placeholder's spot is taken or would block the path
+fn display_placement_check(
+    check: Res<PlacementCheck>,
+    placement_assets: Res<PlacementAssets>,
+    mut query: Query<&mut MeshMaterial3d<StandardMaterial>, With<CursorPlaceholder>>,
+) {
+    if !check.is_changed() {
+        return;
+    }
+    let material = if check.is_valid() {
+        &placement_assets.valid
+    } else {
+        &placement_assets.blocked
+    };
+    for mut cursor_material in query.iter_mut() {
+        cursor_material.0 = material.clone();
+    }
+}
+
+// draws the path enemies currently take, and a ghost of the path they'd take
+// if the tower was placed where the placeholder is
+fn draw_paths(
+    mut gizmos: Gizmos,
+    check: Res<PlacementCheck>,
+    navmeshes: Res<Assets<NavMesh>>,
+    navmesh_query: Query<(&ManagedNavMesh, &NavMeshStatus)>,
+    spawners: Query<(&EnemySpawner, &Transform)>,
+    goals: Query<(&Goal, &Transform)>,
+) {
+    // lift the lines slightly so they aren't hidden by the ground
+    let lift = Vec3::Y * 0.05;
+
+    if let Ok((navmesh_handle, NavMeshStatus::Built)) = navmesh_query.get_single() {
+        if let Some(navmesh) = navmeshes.get(navmesh_handle) {
+            for (spawner, transform) in spawners.iter() {
+                let goals = goals.iter().map(|(goal, to)| (goal.id, to.translation));
+                if let Some(path) = route(navmesh, transform.translation, spawner.goal, goals) {
+                    gizmos.linestrip(
+                        std::iter::once(transform.translation)
+                            .chain(path)
+                            .map(|point| point + lift),
+                        Color::srgb(1.0, 1.0, 0.0),
+                    );
+                }
+            }
+        }
+    }
+
+    if check.blocked {
+        return;
+    }
+    for path in check.paths.iter() {
+        gizmos.linestrip(
+            path.iter().map(|point| *point + lift * 2.0),
+            Color::srgba(0.0, 1.0, 1.0, 0.5),
+        );
+    }
+}
+
+// shows the grid towers are placed on, crossing out the cells they can't be built on
+fn draw_grid(mut gizmos: Gizmos, grid: Res<Grid>) {
+    let flat = Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
+    gizmos.grid(
+        Isometry3d::new(grid.center() + Vec3::Y * 0.01, flat),
+        grid.size(),
+        Vec2::ONE,
+        Color::srgba(1.0, 1.0, 1.0, 0.05),
+    );
+    for cell in grid.unbuildable_cells() {
+        let center = cell_center(cell) + Vec3::Y * 0.01;
+        let color = Color::srgba(1.0, 0.0, 0.0, 0.2);
+        gizmos.line(
+            center + Vec3::new(-0.4, 0.0, -0.4),
+            center + Vec3::new(0.4, 0.0, 0.4),
+            color,
+        );
+        gizmos.line(
+            center + Vec3::new(-0.4, 0.0, 0.4),
+            center + Vec3::new(0.4, 0.0, -0.4),
+            color,
+        );
+    }
+}
+
+// outlines the cells the placeholder's footprint would cover, each with the element of the
+// die face that would land on it
+fn draw_footprint(mut gizmos: Gizmos, check: Res<PlacementCheck>, tower_pool: Res<TowerPool>) {
+    let Some(tower) = tower_pool.towers.get(tower_pool.highlighted) else {
+        return;
+    };
+    let color = if check.is_valid() {
+        Color::srgb(0.0, 1.0, 0.0)
+    } else {
+        Color::srgb(1.0, 0.0, 0.0)
+    };
+    for (cell, face) in check.cells.iter().zip(tower.faces.iter()) {
+        let isometry = Isometry3d::new(
+            cell_center(*cell) + Vec3::Y * 0.05,
+            Quat::from_rotation_x(std::f32::consts::FRAC_PI_2),
+        );
+        gizmos.rect(isometry, Vec2::splat(0.9), color);
+        gizmos.rect(isometry, Vec2::splat(0.5), face.primary_type.color());
+    }
+}
+
 fn place_tower(
     action_state: Res<ActionState<PlacementAction>>,
     mut commands: Commands,
@@ -219,41 +645,116 @@ fn place_tower(
     res: Res<Assets<Gltf>>,
     assets_gltfmesh: Res<Assets<GltfMesh>>,
     mut tower_pool: ResMut<TowerPool>,
+    placement_assets: Res<PlacementAssets>,
     placeholder_query: Query<&Transform, With<TowerPlaceholder>>,
+    mut settings_query: Query<(&mut TargetingMode, &mut Footprint), With<TowerPlaceholder>>,
+    check: Res<PlacementCheck>,
+    mut grid: ResMut<Grid>,
 ) {
     if action_state.just_pressed(&PlacementAction::PlaceTower) {
-        let placeholder_transform = placeholder_query.single();
-        let tower = tower_pool.towers[tower_pool.highlighted];
-        let tower_details = assets_towers.get(tower).unwrap();
+        if tower_pool.towers.is_empty() {
+            return;
+        }
+        let Ok(placeholder_transform) = placeholder_query.get_single() else {
+            return;
+        };
+        let (targeting, footprint) = settings_query.single();
+        let (targeting, footprint) = (*targeting, *footprint);
+        // only place towers on spots that have been checked to be free and not block the path
+        if check.position != Some(placeholder_transform.translation)
+            || check.footprint != Some(footprint)
+        {
+            return;
+        }
+        if check.cells.iter().any(|cell| !grid.is_buildable(*cell)) {
+            info!("Can't place tower, its footprint covers a cell that can't be built on");
+            return;
+        }
+        if check.blocked {
+            info!("Can't place tower, it would block the path");
+            return;
+        }
+        let tower = tower_pool.towers[tower_pool.highlighted].clone();
+        let tower_details = assets_towers.get(tower.details).unwrap();
+        let (bonus_damage, bonus_effects) = face_bonuses(&tower_details.element_type, &tower.faces);
         let gltf = res.get(&tower_details.model).unwrap();
         let mesh = assets_gltfmesh.get(&gltf.meshes[0]).unwrap();
         let mesh3d = mesh.primitives[0].mesh.clone();
         let mat = gltf.materials[0].clone();
-        commands.spawn((
-            Mesh3d(mesh3d),
-            Transform::from_translation(placeholder_transform.translation),
-            MeshMaterial3d(mat),
-            Tower {
-                name: tower_details.name.clone(),
-                element_type: tower_details.element_type.clone(),
-                attack_speed: Timer::from_seconds(1.0, TimerMode::Repeating),
-            },
-            Obstacle,
-        ));
+        let translation = placeholder_transform.translation;
+        let tower_entity = commands
+            .spawn((
+                Mesh3d(mesh3d),
+                Transform::from_translation(translation),
+                MeshMaterial3d(mat),
+                Tower {
+                    name: tower_details.name.clone(),
+                    element_type: tower_details.element_type.clone(),
+                    attack_speed: Timer::from_seconds(
+                        tower_details.attack_interval,
+                        TimerMode::Once,
+                    ),
+                    range: tower_details.range + tower.rarity.range_bonus(),
+                    damage: ((tower_details.damage + bonus_damage) as f32
+                        * tower.rarity.damage_multiplier())
+                    .round() as u32,
+                    projectile_speed: tower_details.projectile_speed,
+                    projectile_model: tower_details.projectile_model.clone(),
+                    projectile: tower_details.projectile.clone(),
+                    projectile_color: tower_details.projectile_color,
+                    projectile_size: tower_details.projectile_size,
+                    effects: [tower_details.effects.clone(), bonus_effects].concat(),
+                    can_target_ground: tower_details.can_target_ground,
+                    can_target_air: tower_details.can_target_air,
+                    details: tower.details,
+                    tier: 0,
+                    value: tower.value,
+                    rarity: tower.rarity,
+                    bonus_damage,
+                },
+                targeting,
+                footprint,
+                OnGameScreen,
+            ))
+            .with_children(|parent| {
+                // each cell of the footprint is its own obstacle, so enemies can
+                // walk around the shape rather than its bounding box
+                for (cell, face) in check.cells.iter().zip(tower.faces) {
+                    let material = placement_assets.tile_materials[&face.primary_type].clone();
+                    let offset = cell_center(*cell) - translation.with_y(0.0);
+                    parent.spawn((
+                        Mesh3d(placement_assets.tile_mesh.clone()),
+                        MeshMaterial3d(material),
+                        Transform::from_translation(offset),
+                        FootprintCell { cell: *cell, face },
+                        Obstacle,
+                    ));
+                }
+            })
+            .id();
+        for cell in check.cells.iter() {
+            grid.occupy(*cell, tower_entity);
+        }
 
-        let idx = tower_pool.highlighted;
-        tower_pool.towers.remove(idx);
+        tower_pool.remove_highlighted();
+        reset_placeholder(&tower_pool, &assets_towers, &mut settings_query);
     }
 }
 
 fn display_tower_pool(
     tower_pool: Res<TowerPool>,
     assets_towers: Res<Assets<TowerDetails>>,
+    targeting_query: Query<&TargetingMode, With<TowerPlaceholder>>,
     mut query: Query<&mut Text, With<OnPlacementOverlay>>,
 ) {
+    let targeting = targeting_query
+        .get_single()
+        .map(|targeting| format!("Targeting: {}\n\n", targeting))
+        .unwrap_or_default();
     for mut text in query.iter_mut() {
         text.0 = format!(
-            "Towers\n\n{}",
+            "{}Towers\n\n{}",
+            targeting,
             tower_pool
                 .towers
                 .iter()
@@ -264,8 +765,26 @@ fn display_tower_pool(
                     } else {
                         "   "
                     };
-                    let tower_details = assets_towers.get(*tower).unwrap();
-                    format!("{}{}", prefix, tower_details.name)
+                    let tower_details = assets_towers.get(tower.details).unwrap();
+                    let faces = tower
+                        .faces
+                        .iter()
+                        .map(|face| face.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ");
+                    let targets = match (
+                        tower_details.can_target_ground,
+                        tower_details.can_target_air,
+                    ) {
+                        (true, true) => "",
+                        (true, false) => " [ground only]",
+                        (false, true) => " [air only]",
+                        (false, false) => " [can't attack]",
+                    };
+                    format!(
+                        "{}{} [{}] ({}){}",
+                        prefix, tower_details.name, tower.rarity, faces, targets
+                    )
                 })
                 .collect::<Vec<String>>()
                 .join("\n")
@@ -273,15 +792,279 @@ fn display_tower_pool(
     }
 }
 
+// switches between placing towers from the pool and picking placed towers
+fn toggle_selection(
+    action_state: Res<ActionState<PlacementAction>>,
+    mut mode: ResMut<PlacementMode>,
+    mut check: ResMut<PlacementCheck>,
+    mut placeholder_query: Query<&mut Visibility, With<TowerPlaceholder>>,
+) {
+    if !action_state.just_pressed(&PlacementAction::ToggleSelection) {
+        return;
+    }
+    *mode = match *mode {
+        PlacementMode::Placing => PlacementMode::Selecting,
+        PlacementMode::Selecting => PlacementMode::Placing,
+    };
+    // the placeholder and the path it would cause are only shown while placing
+    *check = PlacementCheck::default();
+    for mut visibility in placeholder_query.iter_mut() {
+        *visibility = match *mode {
+            PlacementMode::Placing => Visibility::Inherited,
+            PlacementMode::Selecting => Visibility::Hidden,
+        };
+    }
+}
+
+// the placed tower covering the cell under the cursor
+fn selected_tower(
+    grid: &Grid,
+    cursor_query: &Query<&Transform, With<CursorPlaceholder>>,
+) -> Option<Entity> {
+    let cursor_transform = cursor_query.get_single().ok()?;
+    grid.occupant(cell_at(cursor_transform.translation))
+}
+
+fn sell_tower(
+    action_state: Res<ActionState<PlacementAction>>,
+    mut commands: Commands,
+    mut economy: ResMut<Economy>,
+    grid: Res<Grid>,
+    cursor_query: Query<&Transform, With<CursorPlaceholder>>,
+    towers: Query<&Tower>,
+) {
+    if !action_state.just_pressed(&PlacementAction::SellTower) {
+        return;
+    }
+    let Some(entity) = selected_tower(&grid, &cursor_query) else {
+        return;
+    };
+    let Ok(tower) = towers.get(entity) else {
+        return;
+    };
+    info!("Sold {} for {}", tower.name, tower.sell_value());
+    economy.money += tower.sell_value();
+    commands.entity(entity).despawn_recursive();
+}
+
+fn upgrade_tower(
+    action_state: Res<ActionState<PlacementAction>>,
+    mut economy: ResMut<Economy>,
+    grid: Res<Grid>,
+    assets_towers: Res<Assets<TowerDetails>>,
+    res: Res<Assets<Gltf>>,
+    assets_gltfmesh: Res<Assets<GltfMesh>>,
+    cursor_query: Query<&Transform, With<CursorPlaceholder>>,
+    mut towers: Query<(
+        &mut Tower,
+        &mut Mesh3d,
+        &mut MeshMaterial3d<StandardMaterial>,
+    )>,
+) {
+    if !action_state.just_pressed(&PlacementAction::UpgradeTower) {
+        return;
+    }
+    let Some(entity) = selected_tower(&grid, &cursor_query) else {
+        return;
+    };
+    let Ok((mut tower, mut mesh3d, mut material)) = towers.get_mut(entity) else {
+        return;
+    };
+    let tower_details = assets_towers.get(tower.details).unwrap();
+    let Some(upgrade) = tower_details.upgrades.get(tower.tier) else {
+        info!("{} is fully upgraded", tower.name);
+        return;
+    };
+    if economy.money < upgrade.cost {
+        info!("Can't afford to upgrade {}", tower.name);
+        return;
+    }
+    economy.money -= upgrade.cost;
+    tower.upgrade(upgrade);
+    info!("Upgraded {} to tier {}", tower.name, tower.tier);
+    if let Some(gltf) = upgrade.model.as_ref().and_then(|model| res.get(model)) {
+        let mesh = assets_gltfmesh.get(&gltf.meshes[0]).unwrap();
+        mesh3d.0 = mesh.primitives[0].mesh.clone();
+        material.0 = gltf.materials[0].clone();
+    }
+}
+
+// fuses the highlighted pooled tower, or the selected placed tower, with others of the same
+// kind and rarity into one of the next rarity, using up pooled towers before placed ones
+fn merge_towers(
+    action_state: Res<ActionState<PlacementAction>>,
+    mut commands: Commands,
+    mode: Res<PlacementMode>,
+    mut tower_pool: ResMut<TowerPool>,
+    grid: Res<Grid>,
+    assets_towers: Res<Assets<TowerDetails>>,
+    cursor_query: Query<&Transform, With<CursorPlaceholder>>,
+    mut towers: Query<(Entity, &mut Tower)>,
+    mut settings_query: Query<(&mut TargetingMode, &mut Footprint), With<TowerPlaceholder>>,
+) {
+    if !action_state.just_pressed(&PlacementAction::MergeTowers) {
+        return;
+    }
+    // the tower that's kept, either in the pool or on the board
+    let (details, rarity, kept_entity) = match *mode {
+        PlacementMode::Placing => {
+            let Some(tower) = tower_pool.towers.get(tower_pool.highlighted) else {
+                return;
+            };
+            (tower.details, tower.rarity, None)
+        }
+        PlacementMode::Selecting => {
+            let Some((entity, tower)) =
+                selected_tower(&grid, &cursor_query).and_then(|entity| towers.get(entity).ok())
+            else {
+                return;
+            };
+            (tower.details, tower.rarity, Some(entity))
+        }
+    };
+    let tower_details = assets_towers.get(details).unwrap();
+    let name = &tower_details.name;
+    let Some(next) = rarity.next() else {
+        info!("{} is already {}", name, rarity);
+        return;
+    };
+
+    let pooled: Vec<usize> = tower_pool
+        .towers
+        .iter()
+        .enumerate()
+        .filter(|(i, tower)| {
+            tower.details == details
+                && tower.rarity == rarity
+                && (kept_entity.is_some() || *i != tower_pool.highlighted)
+        })
+        .map(|(i, _)| i)
+        .collect();
+    let placed: Vec<Entity> = towers
+        .iter()
+        .filter(|(entity, tower)| {
+            tower.details == details && tower.rarity == rarity && Some(*entity) != kept_entity
+        })
+        .map(|(entity, _)| entity)
+        .collect();
+    if pooled.len() + placed.len() < MERGE_COUNT - 1 {
+        info!("Need {} {} {} towers to merge", MERGE_COUNT, rarity, name);
+        return;
+    }
+
+    let mut value = 0;
+    let from_pool = pooled.len().min(MERGE_COUNT - 1);
+    // removed from the back, so the earlier indices still point at the same towers
+    for i in pooled[..from_pool].iter().rev() {
+        value += tower_pool.remove(*i).value;
+    }
+    for entity in placed.iter().take(MERGE_COUNT - 1 - from_pool) {
+        value += towers.get(*entity).unwrap().1.value;
+        commands.entity(*entity).despawn_recursive();
+    }
+
+    match kept_entity {
+        Some(entity) => {
+            let (_, mut tower) = towers.get_mut(entity).unwrap();
+            tower.promote(next, tower_details);
+            tower.value += value;
+        }
+        None => {
+            let highlighted = tower_pool.highlighted;
+            let tower = &mut tower_pool.towers[highlighted];
+            tower.rarity = next;
+            tower.value += value;
+        }
+    }
+    info!("Merged {} {} towers into a {} one", MERGE_COUNT, name, next);
+    // the pool may have lost the tower the placeholder was showing
+    reset_placeholder(&tower_pool, &assets_towers, &mut settings_query);
+}
+
+// outlines the selected tower's footprint and range, or the cell under the cursor if
+// there's no tower there
+fn draw_selection(
+    mut gizmos: Gizmos,
+    grid: Res<Grid>,
+    cursor_query: Query<&Transform, With<CursorPlaceholder>>,
+    towers: Query<(&Transform, &Tower, &Children)>,
+    cells: Query<&FootprintCell>,
+) {
+    let flat = Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
+    let Some((transform, tower, children)) =
+        selected_tower(&grid, &cursor_query).and_then(|entity| towers.get(entity).ok())
+    else {
+        if let Ok(cursor_transform) = cursor_query.get_single() {
+            let center = cell_center(cell_at(cursor_transform.translation)) + Vec3::Y * 0.05;
+            gizmos.rect(
+                Isometry3d::new(center, flat),
+                Vec2::splat(0.9),
+                Color::srgb(1.0, 1.0, 1.0),
+            );
+        }
+        return;
+    };
+    let color = Color::srgb(1.0, 1.0, 0.0);
+    for cell in cells.iter_many(children) {
+        gizmos.rect(
+            Isometry3d::new(cell_center(cell.cell) + Vec3::Y * 0.05, flat),
+            Vec2::splat(0.9),
+            color,
+        );
+    }
+    gizmos.circle(
+        Isometry3d::new(transform.translation.with_y(0.05), flat),
+        tower.range,
+        color,
+    );
+}
+
+fn display_selection(
+    grid: Res<Grid>,
+    economy: Res<Economy>,
+    assets_towers: Res<Assets<TowerDetails>>,
+    cursor_query: Query<&Transform, With<CursorPlaceholder>>,
+    towers: Query<(&Tower, &TargetingMode)>,
+    mut query: Query<&mut Text, With<OnPlacementOverlay>>,
+) {
+    let selected = selected_tower(&grid, &cursor_query).and_then(|entity| towers.get(entity).ok());
+    let stats = match selected {
+        Some((tower, targeting)) => {
+            let tower_details = assets_towers.get(tower.details).unwrap();
+            let upgrade = match tower_details.upgrades.get(tower.tier) {
+                Some(upgrade) => format!("Upgrade: {} (U)", upgrade.cost),
+                None => "Fully upgraded".to_string(),
+            };
+            format!(
+                "{} ({}, {})\nTier: {}/{}\nDamage: {}\nRange: {:.1}\nAttacks every {:.1}s\n\
+                 Projectile: {:?}\nTargeting: {}\n\n{}\nSell: {} (X)\nMerge: {} alike (M)",
+                tower.name,
+                tower.element_type,
+                tower.rarity,
+                tower.tier,
+                tower_details.upgrades.len(),
+                tower.damage,
+                tower.range,
+                tower.attack_speed.duration().as_secs_f32(),
+                tower.projectile,
+                targeting,
+                upgrade,
+                tower.sell_value(),
+                MERGE_COUNT,
+            )
+        }
+        None => "Move the cursor over a tower".to_string(),
+    };
+    for mut text in query.iter_mut() {
+        text.0 = format!("Selecting\nMoney: {}\n\n{}", economy.money, stats);
+    }
+}
+
 fn start_wave(
     action_state: Res<ActionState<PlacementAction>>,
     mut next_state: ResMut<NextState<GamePlayState>>,
-    mut commands: Commands,
 ) {
     if action_state.just_pressed(&PlacementAction::EndPlacement) {
         next_state.set(GamePlayState::Wave);
-        commands.spawn(Wave {
-            timer: Timer::from_seconds(20.0, TimerMode::Once),
-        });
     }
 }
@@ -0,0 +1,322 @@
+use bevy::{gltf::GltfMesh, prelude::*, utils::HashMap};
+
+use crate::{despawn_screen, GameState};
+
+use super::{placement::Tower, status::StatusEffect, wave::Enemy, BaseElementType, GamePlayState};
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EnemyHitEvent>()
+            .add_systems(OnEnter(GameState::Game), setup)
+            .add_systems(
+                Update,
+                (
+                    // projectiles that land are gone before anything else can hit with them
+                    move_projectile.before(bullet_collision),
+                    bullet_despawn,
+                    bullet_collision,
+                    fade_flashes,
+                )
+                    .run_if(in_state(GameState::Game).and(in_state(GamePlayState::Wave))),
+            )
+            .add_systems(
+                OnExit(GamePlayState::Wave),
+                (despawn_screen::<Projectile>, despawn_screen::<Flash>),
+            );
+    }
+}
+
+// how long beams and splashes stay on screen
+const FLASH_SECONDS: f32 = 0.15;
+
+/// Sent when a tower's attack lands on an enemy, before the enemy's element, armor and
+/// shields are taken into account.
+#[derive(Event, Debug, Clone)]
+pub struct EnemyHitEvent {
+    pub enemy: Entity,
+    pub damage: u32,
+    pub element_type: BaseElementType,
+    pub effects: Vec<StatusEffect>,
+}
+
+#[derive(Reflect, Component)]
+#[reflect(Component)]
+pub struct Projectile {
+    pub speed: f32,
+    pub damage: u32,
+    pub element_type: BaseElementType,
+    pub effects: Vec<StatusEffect>,
+    pub flight: Flight,
+    pub lifetime: Timer,
+}
+
+impl Projectile {
+    fn hit(&self, enemy: Entity) -> EnemyHitEvent {
+        EnemyHitEvent {
+            enemy,
+            damage: self.damage,
+            element_type: self.element_type.clone(),
+            effects: self.effects.clone(),
+        }
+    }
+}
+
+/// How a projectile makes its way to enemies.
+#[derive(Reflect, Debug, Clone)]
+pub enum Flight {
+    /// Following an enemy until it hits
+    Homing { target: Entity },
+    /// Lobbed from one point to another, hurting every enemy near where it lands
+    Arcing {
+        from: Vec3,
+        to: Vec3,
+        height: f32,
+        splash_radius: f32,
+        // how far along the arc it is, from 0 to 1
+        progress: f32,
+    },
+    /// Flying straight on through the enemies in its way
+    Piercing {
+        direction: Vec3,
+        hits_left: u32,
+        // enemies it has already gone through, which it can't hit again
+        hit: Vec<Entity>,
+    },
+}
+
+/// A beam or splash, shown for a moment.
+#[derive(Component)]
+pub struct Flash(Timer);
+
+/// Meshes and materials shared by every projectile, rather than made for each shot.
+#[derive(Resource)]
+pub struct ProjectileAssets {
+    // sphere of radius 1, scaled to each tower's projectile size
+    sphere: Handle<Mesh>,
+    // upright cylinder of radius and height 1, stretched between the ends of each beam
+    beam: Handle<Mesh>,
+    // material for the projectiles of each tower, by name
+    materials: HashMap<String, Handle<StandardMaterial>>,
+}
+
+impl ProjectileAssets {
+    // the material for a tower's projectiles, made the first time a tower of its kind fires
+    fn material(
+        &mut self,
+        tower: &Tower,
+        materials: &mut Assets<StandardMaterial>,
+    ) -> Handle<StandardMaterial> {
+        self.materials
+            .entry(tower.name.clone())
+            .or_insert_with(|| {
+                materials.add(StandardMaterial {
+                    base_color: tower.projectile_color,
+                    unlit: true,
+                    ..default()
+                })
+            })
+            .clone()
+    }
+
+    /// A projectile of the tower's at the given position, its own model if it has one,
+    /// otherwise a sphere of its colour.
+    pub fn projectile(
+        &mut self,
+        tower: &Tower,
+        position: Vec3,
+        materials: &mut Assets<StandardMaterial>,
+        res: &Assets<Gltf>,
+        assets_gltfmesh: &Assets<GltfMesh>,
+    ) -> (Mesh3d, MeshMaterial3d<StandardMaterial>, Transform) {
+        let transform = Transform::from_translation(position);
+        if let Some(gltf) = tower
+            .projectile_model
+            .as_ref()
+            .and_then(|model| res.get(model))
+        {
+            let gltf_mesh = assets_gltfmesh.get(&gltf.meshes[0]).unwrap();
+            return (
+                Mesh3d(gltf_mesh.primitives[0].mesh.clone()),
+                MeshMaterial3d(gltf.materials[0].clone()),
+                transform,
+            );
+        }
+        (
+            Mesh3d(self.sphere.clone()),
+            MeshMaterial3d(self.material(tower, materials)),
+            transform.with_scale(Vec3::splat(tower.projectile_size)),
+        )
+    }
+
+    /// A beam of the tower's colour between two points.
+    pub fn beam(
+        &mut self,
+        tower: &Tower,
+        from: Vec3,
+        to: Vec3,
+        materials: &mut Assets<StandardMaterial>,
+    ) -> (Mesh3d, MeshMaterial3d<StandardMaterial>, Transform, Flash) {
+        let length = from.distance(to);
+        (
+            Mesh3d(self.beam.clone()),
+            MeshMaterial3d(self.material(tower, materials)),
+            Transform::from_translation(from.lerp(to, 0.5))
+                .with_rotation(Quat::from_rotation_arc(
+                    Vec3::Y,
+                    (to - from).normalize_or(Vec3::Y),
+                ))
+                .with_scale(Vec3::new(
+                    tower.projectile_size,
+                    length,
+                    tower.projectile_size,
+                )),
+            Flash(Timer::from_seconds(FLASH_SECONDS, TimerMode::Once)),
+        )
+    }
+
+    // a flattened sphere covering the area a projectile splashed
+    fn splash(
+        &self,
+        material: Handle<StandardMaterial>,
+        position: Vec3,
+        radius: f32,
+    ) -> (Mesh3d, MeshMaterial3d<StandardMaterial>, Transform, Flash) {
+        (
+            Mesh3d(self.sphere.clone()),
+            MeshMaterial3d(material),
+            Transform::from_translation(position).with_scale(Vec3::new(radius, 0.1, radius)),
+            Flash(Timer::from_seconds(FLASH_SECONDS, TimerMode::Once)),
+        )
+    }
+}
+
+fn setup(mut commands: Commands, mut assets_mesh: ResMut<Assets<Mesh>>) {
+    commands.insert_resource(ProjectileAssets {
+        sphere: assets_mesh.add(Sphere::new(1.0)),
+        beam: assets_mesh.add(Cylinder::new(1.0, 1.0)),
+        materials: HashMap::default(),
+    });
+}
+
+fn move_projectile(
+    mut commands: Commands,
+    time: Res<Time>,
+    projectile_assets: Res<ProjectileAssets>,
+    mut query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Projectile,
+            &MeshMaterial3d<StandardMaterial>,
+        ),
+        Without<Enemy>,
+    >,
+    enemy_query: Query<(Entity, &Transform), With<Enemy>>,
+    mut ev_hit: EventWriter<EnemyHitEvent>,
+) {
+    for (entity, mut transform, mut projectile, material) in query.iter_mut() {
+        let step = projectile.speed * time.delta_secs();
+        // where an arcing projectile landed, and how far it splashes
+        let landed = match &mut projectile.flight {
+            Flight::Homing { target } => {
+                // copied out so the projectile can be borrowed again to hit it
+                let target = *target;
+                if let Ok((enemy, target)) = enemy_query.get(target) {
+                    let direction = target.translation - transform.translation;
+                    if direction.length() < step {
+                        ev_hit.send(projectile.hit(enemy));
+                        commands.entity(entity).despawn();
+                    } else {
+                        transform.translation += direction.normalize() * step;
+                    }
+                }
+                None
+            }
+            Flight::Arcing {
+                from,
+                to,
+                height,
+                splash_radius,
+                progress,
+            } => {
+                *progress = (*progress + step / from.distance(*to).max(0.01)).min(1.0);
+                transform.translation = from.lerp(*to, *progress)
+                    + Vec3::Y * *height * 4.0 * *progress * (1.0 - *progress);
+                (*progress >= 1.0).then_some((*to, *splash_radius))
+            }
+            Flight::Piercing { direction, .. } => {
+                transform.translation += *direction * step;
+                None
+            }
+        };
+
+        let Some((position, radius)) = landed else {
+            continue;
+        };
+        for (enemy, enemy_transform) in enemy_query.iter() {
+            if enemy_transform.translation.xz().distance(position.xz()) <= radius {
+                ev_hit.send(projectile.hit(enemy));
+            }
+        }
+        commands.spawn(projectile_assets.splash(material.0.clone(), position, radius));
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn bullet_despawn(
+    mut commands: Commands,
+    mut bullets: Query<(Entity, &mut Projectile)>,
+    time: Res<Time>,
+) {
+    for (entity, mut projectile) in &mut bullets {
+        projectile.lifetime.tick(time.delta());
+        if projectile.lifetime.just_finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn bullet_collision(
+    mut commands: Commands,
+    mut bullets: Query<(Entity, &GlobalTransform, &mut Projectile)>,
+    targets: Query<(Entity, &Transform), With<Enemy>>,
+    mut ev_hit: EventWriter<EnemyHitEvent>,
+) {
+    for (bullet, bullet_transform, mut projectile) in &mut bullets {
+        // arcing projectiles only hurt what's near where they land
+        if let Flight::Arcing { .. } = projectile.flight {
+            continue;
+        }
+        for (enemy, target_transform) in &targets {
+            if Vec3::distance(bullet_transform.translation(), target_transform.translation) >= 0.4 {
+                continue;
+            }
+            if let Flight::Piercing { hit, .. } = &projectile.flight {
+                if hit.contains(&enemy) {
+                    continue;
+                }
+            }
+            ev_hit.send(projectile.hit(enemy));
+            // piercing projectiles carry on until they've hit enough enemies
+            if let Flight::Piercing { hits_left, hit, .. } = &mut projectile.flight {
+                hit.push(enemy);
+                *hits_left = hits_left.saturating_sub(1);
+                if *hits_left > 0 {
+                    continue;
+                }
+            }
+            commands.entity(bullet).despawn_recursive();
+            break;
+        }
+    }
+}
+
+fn fade_flashes(mut commands: Commands, time: Res<Time>, mut flashes: Query<(Entity, &mut Flash)>) {
+    for (entity, mut flash) in flashes.iter_mut() {
+        if flash.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
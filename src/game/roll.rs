@@ -80,6 +80,14 @@ fn handle_input(
     mut next_state: ResMut<NextState<GamePlayState>>,
     mut ev_rolled: EventWriter<DieRolledEvent>,
 ) {
+    if action_state.just_pressed(&RollAction::Placement) {
+        next_state.set(GamePlayState::Placement);
+    }
+
+    if die_pool.dice.is_empty() {
+        return;
+    }
+
     if action_state.just_pressed(&RollAction::HighlightLeft) {
         die_pool.highlighted =
             (die_pool.highlighted + die_pool.dice.len() - 1) % die_pool.dice.len();
@@ -90,12 +98,8 @@ fn handle_input(
     }
 
     if action_state.just_pressed(&RollAction::Roll) {
-        let face = die_pool.roll();
-        ev_rolled.send(DieRolledEvent(face));
-    }
-
-    if action_state.just_pressed(&RollAction::Placement) {
-        next_state.set(GamePlayState::Placement);
+        let (die, face) = die_pool.roll();
+        ev_rolled.send(DieRolledEvent { die, face });
     }
 }
 
@@ -0,0 +1,164 @@
+use std::{f32::consts::FRAC_PI_2, mem::discriminant, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::GameState;
+
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<StatusEffects>().add_systems(
+            Update,
+            draw_status_effects.run_if(in_state(GameState::Game)),
+        );
+    }
+}
+
+/// A status effect applied by a tower's projectiles to the enemies they hit.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Reflect)]
+pub struct StatusEffect {
+    pub kind: StatusEffectKind,
+    /// Seconds the effect lasts for
+    pub duration: f32,
+    #[serde(default)]
+    pub stacking: StackingRule,
+}
+
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum StatusEffectKind {
+    /// Damage over time
+    Burn { damage_per_second: f32 },
+    /// Multiplies the enemy's speed
+    Slow { speed_multiplier: f32 },
+    /// Pushes the enemy back along its path over the duration
+    Knockback { distance: f32 },
+    /// Stops the enemy from moving
+    Root,
+}
+
+impl StatusEffectKind {
+    fn color(&self) -> Color {
+        match self {
+            StatusEffectKind::Burn { .. } => Color::srgb(1.0, 0.4, 0.0),
+            StatusEffectKind::Slow { .. } => Color::srgb(0.2, 0.5, 1.0),
+            StatusEffectKind::Knockback { .. } => Color::srgb(0.9, 0.9, 0.9),
+            StatusEffectKind::Root => Color::srgb(0.4, 0.8, 0.2),
+        }
+    }
+}
+
+/// What happens when an effect is applied to an enemy already suffering from it.
+#[derive(serde::Deserialize, Default, Debug, Clone, Copy, PartialEq, Reflect)]
+pub enum StackingRule {
+    /// Restart the existing effect's duration
+    #[default]
+    Refresh,
+    /// Add another instance, up to `max`, after which the oldest is refreshed
+    Stack { max: usize },
+    /// Leave the existing effect alone
+    Ignore,
+}
+
+#[derive(Debug, Reflect)]
+struct ActiveStatusEffect {
+    kind: StatusEffectKind,
+    timer: Timer,
+    // burn damage that hasn't added up to a whole point yet
+    pending_damage: f32,
+}
+
+/// The stack of status effects currently on an enemy.
+#[derive(Component, Default, Debug, Reflect)]
+#[reflect(Component)]
+pub struct StatusEffects(Vec<ActiveStatusEffect>);
+
+impl StatusEffects {
+    pub fn apply(&mut self, effect: &StatusEffect) {
+        // effects are kept in the order they were applied, so the first is the oldest
+        let same_kind = self
+            .0
+            .iter()
+            .position(|active| discriminant(&active.kind) == discriminant(&effect.kind));
+        let count = self
+            .0
+            .iter()
+            .filter(|active| discriminant(&active.kind) == discriminant(&effect.kind))
+            .count();
+        let refresh = match effect.stacking {
+            StackingRule::Refresh => same_kind,
+            StackingRule::Stack { max } if count < max => None,
+            StackingRule::Stack { .. } => same_kind,
+            StackingRule::Ignore if same_kind.is_some() => return,
+            StackingRule::Ignore => None,
+        };
+        match refresh {
+            Some(idx) => {
+                self.0[idx].kind = effect.kind;
+                self.0[idx].timer = Timer::from_seconds(effect.duration, TimerMode::Once);
+            }
+            None => self.0.push(ActiveStatusEffect {
+                kind: effect.kind,
+                timer: Timer::from_seconds(effect.duration, TimerMode::Once),
+                pending_damage: 0.0,
+            }),
+        }
+    }
+
+    /// Advances every effect, dropping the expired ones, and returns the burn damage dealt.
+    pub fn tick(&mut self, delta: Duration) -> u32 {
+        let mut damage = 0;
+        for active in self.0.iter_mut() {
+            active.timer.tick(delta);
+            if let StatusEffectKind::Burn { damage_per_second } = active.kind {
+                active.pending_damage += damage_per_second * delta.as_secs_f32();
+                let whole = active.pending_damage.floor();
+                active.pending_damage -= whole;
+                damage += whole as u32;
+            }
+        }
+        self.0.retain(|active| !active.timer.finished());
+        damage
+    }
+
+    /// Multiplier applied to the enemy's speed, zero while rooted.
+    pub fn speed_multiplier(&self) -> f32 {
+        self.0
+            .iter()
+            .map(|active| match active.kind {
+                StatusEffectKind::Slow { speed_multiplier } => speed_multiplier,
+                StatusEffectKind::Root => 0.0,
+                _ => 1.0,
+            })
+            .product()
+    }
+
+    /// Speed at which the enemy is currently being pushed back.
+    pub fn knockback_speed(&self) -> f32 {
+        self.0
+            .iter()
+            .map(|active| match active.kind {
+                StatusEffectKind::Knockback { distance } => {
+                    distance / active.timer.duration().as_secs_f32().max(f32::EPSILON)
+                }
+                _ => 0.0,
+            })
+            .sum()
+    }
+}
+
+// draws a ring around the feet of an enemy for each effect on it
+fn draw_status_effects(mut gizmos: Gizmos, query: Query<(&GlobalTransform, &StatusEffects)>) {
+    for (transform, effects) in query.iter() {
+        for (i, active) in effects.0.iter().enumerate() {
+            gizmos.circle(
+                Isometry3d::new(
+                    transform.translation() + Vec3::Y * 0.02,
+                    Quat::from_rotation_x(FRAC_PI_2),
+                ),
+                0.3 + 0.05 * i as f32,
+                active.kind.color(),
+            );
+        }
+    }
+}
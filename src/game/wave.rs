@@ -3,41 +3,158 @@ use std::time::Duration;
 use bevy::{gltf::GltfMesh, prelude::*};
 use vleue_navigator::prelude::*;
 
-use crate::GameState;
+use crate::{despawn_screen, GameState};
 
 use super::{
-    economy::Economy,
-    placement::{Projectile, Tower},
-    EnemyDetails, GamePlayState, Goal, Wave,
+    economy::{Economy, WaveIncome},
+    placement::Tower,
+    projectile::{EnemyHitEvent, Flight, Projectile, ProjectileAssets},
+    status::StatusEffects,
+    AllAssets, BaseElementType, ElementChart, EnemyDetails, EnemyPhase, GamePlayState, Goal,
+    MovementType, ProjectileKind, TargetingMode, WaveDetails,
 };
 
 pub struct WavePlugin;
 
 impl Plugin for WavePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (
-                spawn_enemy,
-                find_path,
-                move_enemy,
-                tower_shooting,
-                move_projectile,
-                bullet_despawn,
-                bullet_collision,
-                target_death,
-                enemy_goal_collision,
-                end_wave,
+        app.init_resource::<WaveCounter>()
+            .init_resource::<Lives>()
+            .add_event::<WaveStartedEvent>()
+            .add_event::<WaveCompletedEvent>()
+            .add_event::<EnemyDamagedEvent>()
+            .add_systems(OnEnter(GameState::Game), reset_waves)
+            .add_systems(OnEnter(GamePlayState::Wave), setup_wave)
+            .add_systems(
+                Update,
+                (
+                    spawn_enemy,
+                    find_path,
+                    tick_status_effects,
+                    tick_abilities.before(apply_hits),
+                    advance_phases,
+                    move_enemy,
+                    wobble_enemy_model,
+                    tower_shooting,
+                    apply_hits,
+                    (split_on_death, target_death).chain(),
+                    enemy_goal_collision,
+                    end_wave,
+                )
+                    .run_if(in_state(GameState::Game).and(in_state(GamePlayState::Wave))),
             )
-                .run_if(in_state(GameState::Game).and(in_state(GamePlayState::Wave))),
-        );
+            .add_systems(Update, complete_wave.run_if(in_state(GameState::Game)))
+            .add_systems(
+                OnExit(GamePlayState::Wave),
+                (despawn_screen::<Wave>, despawn_screen::<Enemy>),
+            );
     }
 }
 
 #[derive(Reflect, Component, Default)]
 #[reflect(Component)]
 pub struct EnemySpawner {
-    pub timer: Timer,
+    pub id: usize,
+    // the goal enemies from this spawner head for, the nearest one if not set
+    pub goal: Option<usize>,
+}
+
+/// Number of waves cleared so far, which is also the index of the wave being run.
+#[derive(Resource, Default, Debug)]
+pub struct WaveCounter {
+    pub current: usize,
+}
+
+const STARTING_LIVES: u32 = 20;
+
+// how high above their path flying enemies are drawn
+const FLYING_HEIGHT: f32 = 1.0;
+
+// how much bigger bosses are drawn than other enemies
+const BOSS_SCALE: f32 = 2.0;
+
+/// Lives left before the game is lost, removed as enemies reach the goal.
+#[derive(Resource, Debug)]
+pub struct Lives(pub u32);
+
+impl Default for Lives {
+    fn default() -> Self {
+        Lives(STARTING_LIVES)
+    }
+}
+
+#[derive(Event)]
+pub struct WaveStartedEvent(pub usize);
+
+#[derive(Event)]
+pub struct WaveCompletedEvent {
+    pub wave: usize,
+    pub reward: usize,
+}
+
+/// Sent when a projectile hurts an enemy.
+#[derive(Event)]
+pub struct EnemyDamagedEvent {
+    pub position: Vec3,
+    pub damage: u32,
+}
+
+/// The running state of a wave, built from its `WaveDetails`.
+#[derive(Component)]
+pub struct Wave {
+    index: usize,
+    reward: usize,
+    groups: Vec<SpawnGroup>,
+    boss: Option<BossSpawn>,
+}
+
+struct BossSpawn {
+    enemy: String,
+    spawner: usize,
+    bounty: usize,
+    delay: Timer,
+    spawned: bool,
+}
+
+struct SpawnGroup {
+    enemy: String,
+    remaining: u32,
+    spawner: usize,
+    delay: Timer,
+    interval: Timer,
+}
+
+impl Wave {
+    fn from_details(index: usize, details: &WaveDetails) -> Self {
+        Wave {
+            index,
+            reward: details.reward,
+            groups: details
+                .groups
+                .iter()
+                .map(|group| SpawnGroup {
+                    enemy: group.enemy.clone(),
+                    remaining: group.count,
+                    spawner: group.spawner,
+                    delay: Timer::new(group.delay, TimerMode::Once),
+                    interval: Timer::new(group.interval, TimerMode::Repeating),
+                })
+                .collect(),
+            boss: details.boss.as_ref().map(|boss| BossSpawn {
+                enemy: boss.enemy.clone(),
+                spawner: boss.spawner,
+                bounty: boss.bounty,
+                delay: Timer::new(boss.delay, TimerMode::Once),
+                spawned: false,
+            }),
+        }
+    }
+
+    // true once every group has spawned all of its enemies, and the boss has come out
+    fn finished_spawning(&self) -> bool {
+        self.groups.iter().all(|group| group.remaining == 0)
+            && self.boss.iter().all(|boss| boss.spawned)
+    }
 }
 
 #[derive(Reflect, Component, Default)]
@@ -45,7 +162,256 @@ pub struct EnemySpawner {
 pub struct Enemy {
     name: String,
     health: u32,
+    max_health: u32,
     speed: f32,
+    damage: u32,
+    element_type: BaseElementType,
+    movement: MovementType,
+    // money paid for killing it
+    bounty: usize,
+    // how far the enemy has moved along its path
+    distance_travelled: f32,
+    // waypoints left to walk through to reach the goal
+    path: Vec<Vec3>,
+    // the goal the enemy is heading for, the nearest one if not set
+    goal: Option<usize>,
+}
+
+impl Enemy {
+    fn new(details: &EnemyDetails, goal: Option<usize>) -> Self {
+        Enemy {
+            name: details.name.clone(),
+            health: details.health,
+            max_health: details.health,
+            speed: details.speed,
+            damage: details.damage,
+            element_type: details.element_type.clone(),
+            movement: details.movement,
+            bounty: details.bounty,
+            distance_travelled: 0.0,
+            path: Vec::new(),
+            goal,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn max_health(&self) -> u32 {
+        self.max_health
+    }
+
+    /// Health left, from 0 when dead to 1 when unhurt.
+    pub fn health_fraction(&self) -> f32 {
+        self.health as f32 / self.max_health.max(1) as f32
+    }
+}
+
+/// An enemy shown on its own health bar, which pays out a bounty when killed.
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub struct Boss {
+    bounty: usize,
+}
+
+/// The phases an enemy goes through as it's hurt.
+#[derive(Component, Debug)]
+pub struct EnemyPhases {
+    phases: Vec<EnemyPhase>,
+    // index of the next phase to start
+    next: usize,
+}
+
+impl EnemyPhases {
+    /// The phase the enemy is in, counting from 1 for the one it spawned in.
+    pub fn current(&self) -> usize {
+        self.next + 1
+    }
+
+    pub fn count(&self) -> usize {
+        self.phases.len() + 1
+    }
+}
+
+/// The visible model of an enemy, kept on a child so it can wobble without turning the enemy.
+#[derive(Component)]
+pub struct EnemyModel;
+
+/// A special ability an enemy has on top of walking to the goal.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Reflect)]
+pub enum EnemyAbility {
+    /// Soaks up damage before health, recharging once the enemy hasn't been hurt for a while
+    Shield {
+        amount: u32,
+        recharge_delay: f32,
+        recharge_per_second: f32,
+    },
+    /// Heals every enemy within the radius, itself included, each interval
+    HealAura {
+        radius: f32,
+        amount: u32,
+        interval: f32,
+    },
+    /// Spawns enemies where it dies, which carry on along its path
+    SplitOnDeath { enemy: String, count: u32 },
+    /// Speeds up for a while each interval
+    Dash {
+        speed_multiplier: f32,
+        duration: f32,
+        interval: f32,
+    },
+    /// Taken off the damage of every projectile that hits
+    Armor { amount: u32 },
+}
+
+#[derive(Debug, Reflect)]
+struct ActiveAbility {
+    ability: EnemyAbility,
+    // time until the ability next triggers, or until the shield starts recharging
+    cooldown: Timer,
+    // seconds of dashing left
+    dashing: f32,
+    // shield points left
+    shield: f32,
+}
+
+/// The abilities of an enemy, and how far along each of them is.
+#[derive(Component, Default, Debug, Reflect)]
+#[reflect(Component)]
+pub struct EnemyAbilities(Vec<ActiveAbility>);
+
+impl EnemyAbilities {
+    fn new(abilities: &[EnemyAbility]) -> Self {
+        EnemyAbilities(
+            abilities
+                .iter()
+                .map(|ability| {
+                    let (cooldown, shield) = match ability {
+                        EnemyAbility::Shield {
+                            amount,
+                            recharge_delay,
+                            ..
+                        } => (
+                            Timer::from_seconds(*recharge_delay, TimerMode::Once),
+                            *amount as f32,
+                        ),
+                        EnemyAbility::HealAura { interval, .. }
+                        | EnemyAbility::Dash { interval, .. } => {
+                            (Timer::from_seconds(*interval, TimerMode::Repeating), 0.0)
+                        }
+                        EnemyAbility::SplitOnDeath { .. } | EnemyAbility::Armor { .. } => {
+                            (Timer::default(), 0.0)
+                        }
+                    };
+                    ActiveAbility {
+                        ability: ability.clone(),
+                        cooldown,
+                        dashing: 0.0,
+                        shield,
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Advances the abilities, returning the radius and amount of each heal aura that pulses.
+    fn tick(&mut self, delta: Duration) -> Vec<(f32, u32)> {
+        let mut pulses = vec![];
+        for active in self.0.iter_mut() {
+            active.cooldown.tick(delta);
+            match &active.ability {
+                EnemyAbility::Shield {
+                    amount,
+                    recharge_per_second,
+                    ..
+                } => {
+                    if active.cooldown.finished() {
+                        active.shield = (active.shield + recharge_per_second * delta.as_secs_f32())
+                            .min(*amount as f32);
+                    }
+                }
+                EnemyAbility::HealAura { radius, amount, .. } => {
+                    if active.cooldown.just_finished() {
+                        pulses.push((*radius, *amount));
+                    }
+                }
+                EnemyAbility::Dash { duration, .. } => {
+                    active.dashing = (active.dashing - delta.as_secs_f32()).max(0.0);
+                    if active.cooldown.just_finished() {
+                        active.dashing = *duration;
+                    }
+                }
+                EnemyAbility::SplitOnDeath { .. } | EnemyAbility::Armor { .. } => {}
+            }
+        }
+        pulses
+    }
+
+    /// Damage left over once shields have soaked up what they can.
+    fn absorb(&mut self, damage: u32) -> u32 {
+        // hits that do no harm, e.g. fully armored ones, don't put off recharging
+        if damage == 0 {
+            return 0;
+        }
+        let mut damage = damage as f32;
+        for active in self.0.iter_mut() {
+            if let EnemyAbility::Shield { .. } = active.ability {
+                let absorbed = active.shield.min(damage);
+                active.shield -= absorbed;
+                damage -= absorbed;
+                // being hurt puts off recharging
+                active.cooldown.reset();
+            }
+        }
+        damage.round() as u32
+    }
+
+    fn armor(&self) -> u32 {
+        self.0
+            .iter()
+            .map(|active| match active.ability {
+                EnemyAbility::Armor { amount } => amount,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    fn speed_multiplier(&self) -> f32 {
+        self.0
+            .iter()
+            .map(|active| match active.ability {
+                EnemyAbility::Dash {
+                    speed_multiplier, ..
+                } if active.dashing > 0.0 => speed_multiplier,
+                _ => 1.0,
+            })
+            .product()
+    }
+}
+
+fn reset_waves(mut commands: Commands) {
+    commands.insert_resource(WaveCounter::default());
+    commands.insert_resource(Lives::default());
+}
+
+fn setup_wave(
+    mut commands: Commands,
+    all_assets: Res<AllAssets>,
+    assets_waves: Res<Assets<WaveDetails>>,
+    wave_counter: Res<WaveCounter>,
+    mut ev_started: EventWriter<WaveStartedEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // running out of waves means they've all been cleared
+    let Some(wave_handle) = all_assets.waves.get(wave_counter.current) else {
+        next_state.set(GameState::Victory);
+        return;
+    };
+    let wave_details = assets_waves.get(wave_handle).unwrap();
+    info!("Wave {} started", wave_counter.current + 1);
+    commands.spawn(Wave::from_details(wave_counter.current, wave_details));
+    ev_started.send(WaveStartedEvent(wave_counter.current));
 }
 
 fn spawn_enemy(
@@ -54,164 +420,591 @@ fn spawn_enemy(
     assets_gltfmesh: Res<Assets<GltfMesh>>,
     res: Res<Assets<Gltf>>,
     time: Res<Time>,
-    mut query: Query<(&mut EnemySpawner, &Transform)>,
+    mut wave_query: Query<&mut Wave>,
+    spawner_query: Query<(&EnemySpawner, &Transform)>,
 ) {
-    for (mut spawner, transform) in query.iter_mut() {
-        spawner.timer.tick(time.delta());
-        if spawner.timer.finished() {
-            let enemy = assets_enemies.iter().next().unwrap().1;
-            let enemy_mesh = res.get(&enemy.model).unwrap();
-            let enemy_mesh_mesh = assets_gltfmesh.get(&enemy_mesh.meshes[0]).unwrap();
-
-            commands.spawn((
-                Mesh3d(enemy_mesh_mesh.primitives[0].mesh.clone()),
-                MeshMaterial3d(enemy_mesh.materials[0].clone()),
-                transform.with_scale(Vec3::splat(0.5)),
-                Enemy {
-                    name: enemy.name.clone(),
-                    health: enemy.health,
-                    speed: enemy.speed,
-                },
-            ));
+    for mut wave in wave_query.iter_mut() {
+        // the enemy, spawner and boss bounty of everything coming out this frame
+        let mut spawns = vec![];
+        for group in wave.groups.iter_mut() {
+            if group.remaining == 0 {
+                continue;
+            }
+            group.delay.tick(time.delta());
+            if !group.delay.finished() {
+                continue;
+            }
+            // the first enemy comes out as soon as the delay is over
+            if !group.delay.just_finished() && !group.interval.tick(time.delta()).just_finished() {
+                continue;
+            }
+            group.remaining -= 1;
+            spawns.push((group.enemy.clone(), group.spawner, None));
         }
+        if let Some(boss) = wave.boss.as_mut().filter(|boss| !boss.spawned) {
+            if boss.delay.tick(time.delta()).finished() {
+                boss.spawned = true;
+                spawns.push((boss.enemy.clone(), boss.spawner, Some(boss.bounty)));
+            }
+        }
+
+        for (name, spawner_index, bounty) in spawns {
+            let Some((_, enemy)) = assets_enemies.iter().find(|(_, enemy)| enemy.name == name)
+            else {
+                warn!("unknown enemy in wave: {}", name);
+                continue;
+            };
+            // waves written for maps with more spawners wrap around on maps with fewer
+            let spawner_count = spawner_query.iter().len();
+            let Some((spawner, transform)) = spawner_query
+                .iter()
+                .find(|(spawner, _)| spawner.id == spawner_index % spawner_count.max(1))
+            else {
+                warn!("no spawner for wave: {}", spawner_index);
+                continue;
+            };
+            if bounty.is_some() {
+                info!("Boss {} appeared", name);
+            }
+            spawn_enemy_entity(
+                &mut commands,
+                enemy,
+                Enemy::new(enemy, spawner.goal),
+                bounty.map(|bounty| Boss { bounty }),
+                *transform,
+                &res,
+                &assets_gltfmesh,
+            );
+        }
+    }
+}
+
+fn spawn_enemy_entity(
+    commands: &mut Commands,
+    details: &EnemyDetails,
+    enemy: Enemy,
+    boss: Option<Boss>,
+    transform: Transform,
+    res: &Assets<Gltf>,
+    assets_gltfmesh: &Assets<GltfMesh>,
+) {
+    // flying enemies are lifted off the ground, and bosses drawn bigger
+    let offset = match details.movement {
+        MovementType::Ground => Vec3::ZERO,
+        MovementType::Flying => Vec3::Y * FLYING_HEIGHT,
+    };
+    let scale = if boss.is_some() {
+        0.5 * BOSS_SCALE
+    } else {
+        0.5
+    };
+    let model = enemy_model(
+        &details.model,
+        Transform::from_translation(offset).with_scale(Vec3::splat(scale)),
+        res,
+        assets_gltfmesh,
+    );
+    let mut entity = commands.spawn((
+        transform,
+        Visibility::default(),
+        enemy,
+        StatusEffects::default(),
+        EnemyAbilities::new(&details.abilities),
+    ));
+    entity.with_children(|parent| {
+        parent.spawn(model);
+    });
+    if !details.phases.is_empty() {
+        entity.insert(EnemyPhases {
+            phases: details.phases.clone(),
+            next: 0,
+        });
+    }
+    if let Some(boss) = boss {
+        entity.insert(boss);
     }
 }
 
+fn enemy_model(
+    model: &Handle<Gltf>,
+    transform: Transform,
+    res: &Assets<Gltf>,
+    assets_gltfmesh: &Assets<GltfMesh>,
+) -> (
+    Mesh3d,
+    MeshMaterial3d<StandardMaterial>,
+    Transform,
+    EnemyModel,
+) {
+    let enemy_mesh = res.get(model).unwrap();
+    let enemy_mesh_mesh = assets_gltfmesh.get(&enemy_mesh.meshes[0]).unwrap();
+    (
+        Mesh3d(enemy_mesh_mesh.primitives[0].mesh.clone()),
+        MeshMaterial3d(enemy_mesh.materials[0].clone()),
+        transform,
+        EnemyModel,
+    )
+}
+
+// computes paths for new enemies, and for every enemy when the navmesh has been rebuilt
 pub fn find_path(
-    mut navmeshes: ResMut<Assets<NavMesh>>,
-    navmesh: Query<(&ManagedNavMesh, &NavMeshStatus)>,
-    mut from_query: Query<&mut Transform, With<Enemy>>,
-    to_query: Query<&Transform, (With<Goal>, Without<Enemy>)>,
+    navmeshes: Res<Assets<NavMesh>>,
+    navmesh: Query<(&ManagedNavMesh, Ref<NavMeshStatus>)>,
+    mut from_query: Query<(&Transform, &mut Enemy)>,
+    goals: Query<(&Goal, &Transform), Without<Enemy>>,
 ) {
-    let (navmesh_handle, status) = navmesh.single();
+    let Ok((navmesh_handle, status)) = navmesh.get_single() else {
+        return;
+    };
     if *status != NavMeshStatus::Built {
         return;
     }
-    if let Some(navmesh) = navmeshes.get_mut(navmesh_handle) {
-        let to = to_query.single().translation;
-        from_query.iter_mut().for_each(|mut from| {
-            if let Some(path) = navmesh.transformed_path(from.translation, to) {
-                let next = path.path[0];
-                from.look_at(Vec3::new(next.x, next.y, next.z), Vec3::Y);
+    let rebuilt = status.is_changed();
+    if let Some(navmesh) = navmeshes.get(navmesh_handle) {
+        from_query.iter_mut().for_each(|(from, mut enemy)| {
+            if !rebuilt && !enemy.path.is_empty() {
+                return;
+            }
+            let goals = goals.iter().map(|(goal, to)| (goal.id, to.translation));
+            let path = match enemy.movement {
+                MovementType::Ground => route(navmesh, from.translation, enemy.goal, goals),
+                MovementType::Flying => flight_route(from.translation, enemy.goal, goals),
+            };
+            if let Some(path) = path {
+                enemy.path = path;
             } else {
-                warn_once!("no path found from {:?} to {:?}", from, to);
+                warn_once!("no path found from {:?} to goal {:?}", from, enemy.goal);
             }
         });
     }
 }
 
-fn move_enemy(mut query: Query<&mut Transform, With<Enemy>>) {
-    for mut transform in query.iter_mut() {
-        let forward = transform.forward();
-        transform.translation += forward * 0.01;
-        // base rotate off of z translation
-        transform.rotation = Quat::from_rotation_z((transform.translation.z * 8.0).sin() * 0.1);
-    }
+/// The path from a point to the goal with the given id, or to whichever goal is the
+/// shortest walk away if there isn't one.
+pub fn route(
+    navmesh: &NavMesh,
+    from: Vec3,
+    goal: Option<usize>,
+    goals: impl Iterator<Item = (usize, Vec3)>,
+) -> Option<Vec<Vec3>> {
+    goals
+        .filter(|(id, _)| goal.is_none() || goal == Some(*id))
+        .filter_map(|(_, to)| navmesh.transformed_path(from, to))
+        .map(|path| {
+            let length = std::iter::once(from)
+                .chain(path.path.iter().copied())
+                .zip(path.path.iter())
+                .map(|(a, b)| a.distance(*b))
+                .sum::<f32>();
+            (length, path.path)
+        })
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, path)| path)
 }
 
-fn tower_shooting(
-    mut commands: Commands,
-    query: Query<(Entity, &Transform), With<Enemy>>,
-    mut query_tower: Query<(&Transform, &mut Tower)>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+// a straight line to the goal with the given id, or to the closest goal if there isn't one
+fn flight_route(
+    from: Vec3,
+    goal: Option<usize>,
+    goals: impl Iterator<Item = (usize, Vec3)>,
+) -> Option<Vec<Vec3>> {
+    goals
+        .filter(|(id, _)| goal.is_none() || goal == Some(*id))
+        .min_by(|(_, a), (_, b)| a.distance(from).total_cmp(&b.distance(from)))
+        .map(|(_, to)| vec![to])
+}
+
+fn tick_status_effects(
     time: Res<Time>,
+    mut query: Query<(&mut Enemy, &mut StatusEffects, &mut EnemyAbilities)>,
 ) {
-    for (enemy, enemy_transform) in query.iter() {
-        for (tower_transform, mut tower) in query_tower.iter_mut() {
-            tower.attack_speed.tick(time.delta());
-            if tower.attack_speed.finished() {
-                let bullet_spawn = tower_transform.translation; //  + tower.bullet_offset;
+    for (mut enemy, mut effects, mut abilities) in query.iter_mut() {
+        let damage = effects.tick(time.delta());
+        if damage > 0 {
+            let damage = abilities.absorb(damage);
+            enemy.health = enemy.health.saturating_sub(damage);
+        }
+    }
+}
 
-                let distance = tower_transform
-                    .translation
-                    .distance(enemy_transform.translation);
-
-                let placeholder_mesh = meshes.add(Sphere::new(0.1));
-                if distance < 5.0 {
-                    commands.spawn((
-                        Mesh3d(placeholder_mesh.clone()),
-                        MeshMaterial3d(materials.add(StandardMaterial {
-                            base_color: Color::srgb(1.0, 0.0, 0.0),
-                            ..Default::default()
-                        })),
-                        Transform::from_translation(bullet_spawn),
-                        Projectile {
-                            target: enemy,
-                            speed: 10.0,
-                            damage: 5,
-                            lifetime: Timer::new(Duration::from_secs(5), TimerMode::Once),
-                        },
-                    ));
-                    tower.attack_speed.reset();
-                }
+// recharges shields, times dashes and heals the enemies around each pulsing heal aura
+fn tick_abilities(
+    time: Res<Time>,
+    mut abilities_query: Query<(&Transform, &mut EnemyAbilities)>,
+    mut enemies: Query<(&Transform, &mut Enemy)>,
+) {
+    let mut pulses = vec![];
+    for (transform, mut abilities) in abilities_query.iter_mut() {
+        for (radius, amount) in abilities.tick(time.delta()) {
+            pulses.push((transform.translation, radius, amount));
+        }
+    }
+    for (center, radius, amount) in pulses {
+        for (transform, mut enemy) in enemies.iter_mut() {
+            // the dead can't be healed back, they're on their way out
+            if enemy.health == 0 {
+                continue;
+            }
+            if transform.translation.distance(center) <= radius {
+                enemy.health = (enemy.health + amount).min(enemy.max_health);
             }
         }
     }
 }
 
-fn move_projectile(
+// moves enemies on to their next phase once their health drops low enough
+fn advance_phases(
     mut commands: Commands,
+    assets_gltfmesh: Res<Assets<GltfMesh>>,
+    res: Res<Assets<Gltf>>,
+    mut enemies: Query<(
+        Entity,
+        &mut Enemy,
+        &mut EnemyAbilities,
+        &mut EnemyPhases,
+        &Children,
+    )>,
+    models: Query<&Transform, With<EnemyModel>>,
+) {
+    for (entity, mut enemy, mut abilities, mut phases, children) in enemies.iter_mut() {
+        if enemy.health == 0 {
+            continue;
+        }
+        // several phases can start at once, the model only changes to the last one's
+        let mut new_model = None;
+        while let Some(phase) = phases.phases.get(phases.next).cloned() {
+            if enemy.health_fraction() > phase.health_fraction {
+                break;
+            }
+            phases.next += 1;
+            info!("{} entered phase {}", enemy.name, phases.current());
+            if let Some(speed) = phase.speed {
+                enemy.speed = speed;
+            }
+            if let Some(phase_abilities) = &phase.abilities {
+                *abilities = EnemyAbilities::new(phase_abilities);
+            }
+            new_model = phase.model.or(new_model);
+        }
+        let Some(new_model) = new_model else {
+            continue;
+        };
+        // the new model stands where the old one did
+        for &child in children.iter() {
+            let Ok(transform) = models.get(child) else {
+                continue;
+            };
+            commands.entity(child).despawn_recursive();
+            let model = enemy_model(&new_model, *transform, &res, &assets_gltfmesh);
+            commands.entity(entity).with_children(|parent| {
+                parent.spawn(model);
+            });
+        }
+    }
+}
+
+fn move_enemy(
     time: Res<Time>,
-    mut query: Query<(Entity, &mut Transform, &Projectile), Without<Enemy>>,
-    enemy_query: Query<&Transform, With<Enemy>>,
+    mut query: Query<(&mut Transform, &mut Enemy, &StatusEffects, &EnemyAbilities)>,
 ) {
-    for (entity, mut transform, projectile) in query.iter_mut() {
-        if let Ok(target) = enemy_query.get(projectile.target) {
-            let direction = target.translation - transform.translation;
-            let distance = direction.length();
-            let velocity = direction.normalize() * projectile.speed * time.delta_secs();
-
-            if distance < velocity.length() {
-                commands.entity(entity).despawn();
+    for (mut transform, mut enemy, effects, abilities) in query.iter_mut() {
+        // walk through as many waypoints as the enemy can reach this frame
+        let mut step = enemy.speed
+            * effects.speed_multiplier()
+            * abilities.speed_multiplier()
+            * time.delta_secs();
+        while step > 0.0 {
+            let Some(&next) = enemy.path.first() else {
+                break;
+            };
+            let distance = transform.translation.distance(next);
+            if distance <= step {
+                transform.translation = next;
+                enemy.path.remove(0);
+                enemy.distance_travelled += distance;
+                step -= distance;
             } else {
-                transform.translation += velocity;
+                let direction = (next - transform.translation) / distance;
+                transform.translation += direction * step;
+                enemy.distance_travelled += step;
+                step = 0.0;
             }
         }
+        if let Some(&next) = enemy.path.first() {
+            let target = Vec3::new(next.x, transform.translation.y, next.z);
+            transform.look_at(target, Vec3::Y);
+        }
+
+        let knockback = effects.knockback_speed() * time.delta_secs();
+        let back = transform.back();
+        transform.translation += back * knockback;
+        enemy.distance_travelled -= knockback;
     }
 }
 
-fn bullet_despawn(
-    mut commands: Commands,
-    mut bullets: Query<(Entity, &mut Projectile)>,
-    time: Res<Time>,
+// rocks the model from side to side as the enemy walks
+fn wobble_enemy_model(
+    enemies: Query<&Enemy>,
+    mut models: Query<(&Parent, &mut Transform), With<EnemyModel>>,
 ) {
-    for (entity, mut projectile) in &mut bullets {
-        projectile.lifetime.tick(time.delta());
-        if projectile.lifetime.just_finished() {
-            commands.entity(entity).despawn_recursive();
+    for (parent, mut transform) in models.iter_mut() {
+        if let Ok(enemy) = enemies.get(parent.get()) {
+            transform.rotation =
+                Quat::from_rotation_z((enemy.distance_travelled * 8.0).sin() * 0.1);
         }
     }
 }
 
-fn bullet_collision(
+fn tower_shooting(
     mut commands: Commands,
-    bullets: Query<(Entity, &GlobalTransform, &Projectile), With<Projectile>>,
-    mut targets: Query<(&mut Enemy, &Transform), With<Enemy>>,
+    query: Query<(Entity, &Transform, &Enemy)>,
+    mut query_tower: Query<(&Transform, &mut Tower, &TargetingMode)>,
+    mut projectile_assets: ResMut<ProjectileAssets>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    assets_gltfmesh: Res<Assets<GltfMesh>>,
+    res: Res<Assets<Gltf>>,
+    time: Res<Time>,
+    mut ev_hit: EventWriter<EnemyHitEvent>,
 ) {
-    for (bullet, bullet_transform, projectile) in &bullets {
-        for (mut enemy, target_transform) in &mut targets {
-            if Vec3::distance(bullet_transform.translation(), target_transform.translation) < 0.4 {
-                commands.entity(bullet).despawn_recursive();
-                enemy.health = enemy
-                    .health
-                    .checked_sub(projectile.damage)
-                    .unwrap_or_default();
-                break;
+    for (tower_transform, mut tower, targeting) in query_tower.iter_mut() {
+        tower.attack_speed.tick(time.delta());
+        if !tower.attack_speed.finished() {
+            continue;
+        }
+        let bullet_spawn = tower_transform.translation; //  + tower.bullet_offset;
+
+        let (can_target_ground, can_target_air) = (tower.can_target_ground, tower.can_target_air);
+        let can_target = |enemy: &Enemy| match enemy.movement {
+            MovementType::Ground => can_target_ground,
+            MovementType::Flying => can_target_air,
+        };
+        let in_range = query.iter().filter(|(_, enemy_transform, enemy)| {
+            can_target(enemy)
+                && tower_transform
+                    .translation
+                    .distance(enemy_transform.translation)
+                    < tower.range
+        });
+        let Some(enemy) = select_target(*targeting, tower_transform.translation, in_range) else {
+            continue;
+        };
+        tower.attack_speed.reset();
+        let target_position = query.get(enemy).unwrap().1.translation;
+        let hit = |enemy: Entity| EnemyHitEvent {
+            enemy,
+            damage: tower.damage,
+            element_type: tower.element_type.clone(),
+            effects: tower.effects.clone(),
+        };
+        // beams are drawn from the middle of the tower to the middle of the enemy
+        let lift = Vec3::Y * 0.5;
+
+        let flight = match tower.projectile {
+            ProjectileKind::Homing => Flight::Homing { target: enemy },
+            ProjectileKind::Arcing {
+                height,
+                splash_radius,
+            } => Flight::Arcing {
+                from: bullet_spawn,
+                to: target_position,
+                height,
+                splash_radius,
+                progress: 0.0,
+            },
+            ProjectileKind::Pierce { hits } => Flight::Piercing {
+                direction: (target_position - bullet_spawn).normalize_or_zero(),
+                hits_left: hits,
+                hit: vec![],
+            },
+            ProjectileKind::Beam => {
+                ev_hit.send(hit(enemy));
+                commands.spawn(projectile_assets.beam(
+                    &tower,
+                    bullet_spawn + lift,
+                    target_position + lift,
+                    &mut materials,
+                ));
+                continue;
             }
+            ProjectileKind::Chain { jumps, range } => {
+                // each jump goes to the nearest enemy in range of the last one hit
+                let mut chain = vec![(enemy, target_position)];
+                while chain.len() <= jumps as usize {
+                    let last = chain[chain.len() - 1].1;
+                    let next = query
+                        .iter()
+                        .filter(|(entity, transform, enemy)| {
+                            can_target(enemy)
+                                && !chain.iter().any(|(hit, _)| hit == entity)
+                                && transform.translation.distance(last) <= range
+                        })
+                        .min_by(|(_, a, _), (_, b, _)| {
+                            a.translation
+                                .distance(last)
+                                .total_cmp(&b.translation.distance(last))
+                        });
+                    let Some((next, transform, _)) = next else {
+                        break;
+                    };
+                    chain.push((next, transform.translation));
+                }
+                let mut from = bullet_spawn;
+                for (enemy, position) in chain {
+                    ev_hit.send(hit(enemy));
+                    commands.spawn(projectile_assets.beam(
+                        &tower,
+                        from + lift,
+                        position + lift,
+                        &mut materials,
+                    ));
+                    from = position;
+                }
+                continue;
+            }
+        };
+        commands.spawn((
+            projectile_assets.projectile(
+                &tower,
+                bullet_spawn,
+                &mut materials,
+                &res,
+                &assets_gltfmesh,
+            ),
+            Projectile {
+                flight,
+                speed: tower.projectile_speed,
+                damage: tower.damage,
+                element_type: tower.element_type.clone(),
+                effects: tower.effects.clone(),
+                lifetime: Timer::new(Duration::from_secs(5), TimerMode::Once),
+            },
+        ));
+    }
+}
+
+// picks the enemy a tower shoots at out of the enemies in its range
+fn select_target<'a>(
+    targeting: TargetingMode,
+    tower_position: Vec3,
+    enemies: impl Iterator<Item = (Entity, &'a Transform, &'a Enemy)>,
+) -> Option<Entity> {
+    let target = match targeting {
+        TargetingMode::First => enemies
+            .max_by(|(_, _, a), (_, _, b)| a.distance_travelled.total_cmp(&b.distance_travelled)),
+        TargetingMode::Last => enemies
+            .min_by(|(_, _, a), (_, _, b)| a.distance_travelled.total_cmp(&b.distance_travelled)),
+        TargetingMode::Strongest => enemies.max_by_key(|(_, _, enemy)| enemy.health),
+        TargetingMode::Weakest => enemies.min_by_key(|(_, _, enemy)| enemy.health),
+        TargetingMode::Closest => enemies.min_by(|(_, a, _), (_, b, _)| {
+            let distance_a = a.translation.distance(tower_position);
+            distance_a.total_cmp(&b.translation.distance(tower_position))
+        }),
+    };
+    target.map(|(entity, _, _)| entity)
+}
+
+// hurts enemies hit by towers, taking their element, armor and shields into account
+fn apply_hits(
+    all_assets: Res<AllAssets>,
+    assets_elements: Res<Assets<ElementChart>>,
+    mut ev_hit: EventReader<EnemyHitEvent>,
+    mut targets: Query<(
+        &mut Enemy,
+        &mut StatusEffects,
+        &mut EnemyAbilities,
+        &Transform,
+    )>,
+    mut ev_damaged: EventWriter<EnemyDamagedEvent>,
+) {
+    let chart = assets_elements.get(&all_assets.elements).unwrap();
+    for hit in ev_hit.read() {
+        let Ok((mut enemy, mut effects, mut abilities, transform)) = targets.get_mut(hit.enemy)
+        else {
+            continue;
+        };
+        if enemy.health == 0 {
+            continue;
+        }
+        let multiplier = chart.multiplier(&hit.element_type, &enemy.element_type);
+        let damage = (hit.damage as f32 * multiplier).round() as u32;
+        let damage = abilities.absorb(damage.saturating_sub(abilities.armor()));
+        enemy.health = enemy.health.checked_sub(damage).unwrap_or_default();
+        // fully armored or absorbed hits leave no number behind
+        if damage > 0 {
+            ev_damaged.send(EnemyDamagedEvent {
+                position: transform.translation,
+                damage,
+            });
+        }
+        for effect in hit.effects.iter() {
+            effects.apply(effect);
         }
     }
 }
 
 fn enemy_goal_collision(
     mut commands: Commands,
-    goals: Query<&Transform, With<Goal>>,
-    enemies: Query<(Entity, &Transform), With<Enemy>>,
+    goals: Query<(&Goal, &Transform)>,
+    enemies: Query<(Entity, &Transform, &Enemy)>,
+    mut lives: ResMut<Lives>,
+    mut income: ResMut<WaveIncome>,
+    mut next_state: ResMut<NextState<GameState>>,
 ) {
-    for goal_transform in &goals {
-        for (entity, enemy_transform) in &enemies {
+    for (goal, goal_transform) in &goals {
+        for (entity, enemy_transform, enemy) in &enemies {
+            // enemies heading elsewhere walk straight past other goals
+            if enemy.goal.is_some_and(|id| id != goal.id) {
+                continue;
+            }
             if Vec3::distance(goal_transform.translation, enemy_transform.translation) < 0.4 {
                 commands.entity(entity).despawn_recursive();
+                lives.0 = lives.0.saturating_sub(enemy.damage);
+                income.leaked += 1;
+                if lives.0 == 0 {
+                    info!("Game over");
+                    next_state.set(GameState::GameOver);
+                }
+            }
+        }
+    }
+}
+
+// spawns the enemies that dying enemies split into, picking up where their parent left off
+fn split_on_death(
+    mut commands: Commands,
+    assets_enemies: Res<Assets<EnemyDetails>>,
+    assets_gltfmesh: Res<Assets<GltfMesh>>,
+    res: Res<Assets<Gltf>>,
+    enemies: Query<(&Enemy, &EnemyAbilities, &Transform)>,
+) {
+    for (parent, abilities, transform) in &enemies {
+        if parent.health > 0 {
+            continue;
+        }
+        for active in abilities.0.iter() {
+            let EnemyAbility::SplitOnDeath { enemy, count } = &active.ability else {
+                continue;
+            };
+            let Some((_, details)) = assets_enemies
+                .iter()
+                .find(|(_, details)| &details.name == enemy)
+            else {
+                warn!("unknown enemy to split into: {}", enemy);
+                continue;
+            };
+            for _ in 0..*count {
+                let mut child = Enemy::new(details, parent.goal);
+                child.path = parent.path.clone();
+                child.distance_travelled = parent.distance_travelled;
+                spawn_enemy_entity(
+                    &mut commands,
+                    details,
+                    child,
+                    None,
+                    *transform,
+                    &res,
+                    &assets_gltfmesh,
+                );
             }
         }
     }
@@ -219,18 +1012,28 @@ fn enemy_goal_collision(
 
 fn target_death(
     mut commands: Commands,
-    enemies: Query<(Entity, &Enemy)>,
+    enemies: Query<(Entity, &Enemy, Option<&Boss>)>,
     projectiles: Query<(Entity, &Projectile)>,
     mut economy: ResMut<Economy>,
+    mut income: ResMut<WaveIncome>,
 ) {
-    for (ent, enemy) in &enemies {
+    for (ent, enemy, boss) in &enemies {
         if enemy.health == 0 {
             commands.entity(ent).despawn_recursive();
-            economy.money += 10;
+            economy.money += enemy.bounty;
+            income.bounties += enemy.bounty;
+            if let Some(boss) = boss {
+                info!("Boss {} defeated", enemy.name);
+                economy.money += boss.bounty;
+                income.boss_bounties += boss.bounty;
+            }
         }
     }
     for (ent, projectile) in &projectiles {
-        if let Err(_) = enemies.get(projectile.target) {
+        let Flight::Homing { target } = projectile.flight else {
+            continue;
+        };
+        if let Err(_) = enemies.get(target) {
             commands.entity(ent).despawn_recursive();
         }
     }
@@ -238,23 +1041,33 @@ fn target_death(
 
 fn end_wave(
     mut next_state: ResMut<NextState<GamePlayState>>,
-    time: Res<Time>,
-    mut wave_query: Query<&mut Wave>,
-    mut enemy_query: Query<Entity, With<Enemy>>,
+    wave_query: Query<&Wave>,
+    enemy_query: Query<Entity, With<Enemy>>,
+    mut ev_completed: EventWriter<WaveCompletedEvent>,
 ) {
-    for mut wave in wave_query.iter_mut() {
-        wave.timer.tick(time.delta());
-        if wave.timer.finished() {
-            let mut all_enemies_dead = true;
-            for _ in enemy_query.iter_mut() {
-                all_enemies_dead = false;
-                break;
-            }
+    for wave in wave_query.iter() {
+        if wave.finished_spawning() && enemy_query.is_empty() {
+            info!("Wave ended");
+            ev_completed.send(WaveCompletedEvent {
+                wave: wave.index,
+                reward: wave.reward,
+            });
+            next_state.set(GamePlayState::Economy);
+        }
+    }
+}
 
-            if all_enemies_dead {
-                info!("Wave ended");
-                next_state.set(GamePlayState::Economy);
-            }
+fn complete_wave(
+    all_assets: Res<AllAssets>,
+    mut wave_counter: ResMut<WaveCounter>,
+    mut ev_completed: EventReader<WaveCompletedEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for ev in ev_completed.read() {
+        wave_counter.current = ev.wave + 1;
+        if wave_counter.current >= all_assets.waves.len() {
+            info!("All waves cleared");
+            next_state.set(GameState::Victory);
         }
     }
 }
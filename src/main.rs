@@ -3,6 +3,8 @@ mod debug;
 mod game;
 mod input;
 mod menu;
+mod results;
+mod settings;
 mod splash;
 
 use bevy::{asset::AssetMetaCheck, prelude::*};
@@ -16,6 +18,8 @@ enum GameState {
     Splash,
     Menu,
     Game,
+    GameOver,
+    Victory,
 }
 
 fn main() {
@@ -35,6 +39,8 @@ fn main() {
             splash::SplashPlugin,
             menu::MenuPlugin,
             game::GamePlugin,
+            results::ResultsPlugin,
+            settings::SettingsPlugin,
             input::InputModeManagerPlugin,
             #[cfg(feature = "debug")]
             debug::DebugPlugin,
@@ -1,4 +1,8 @@
-use crate::GAME_NAME;
+use crate::{
+    game::{MapAssets, MapDetails, SelectedMap},
+    settings::Settings,
+    GAME_NAME,
+};
 
 use super::{despawn_screen, GameState};
 
@@ -21,9 +25,13 @@ impl Plugin for MenuPlugin {
 struct OnMenuScreen;
 
 fn ui(
+    mut commands: Commands,
     mut contexts: EguiContexts,
     mut next_state: ResMut<NextState<GameState>>,
     mut writer: EventWriter<AppExit>,
+    map_assets: Res<MapAssets>,
+    assets_maps: Res<Assets<MapDetails>>,
+    mut settings: ResMut<Settings>,
 ) {
     let ctx = contexts.ctx_mut();
 
@@ -35,13 +43,34 @@ fn ui(
 
             ui.add_space(10.0);
 
-            let play = ui.add(egui::Button::new(egui::RichText::new("Play").size(32.0)));
-            let quit = ui.add(egui::Button::new(egui::RichText::new("Quit").size(24.0)));
+            ui.add(egui::Label::new(
+                egui::RichText::new("Select level").size(24.0),
+            ));
 
-            if play.clicked() {
-                next_state.set(GameState::Game);
+            // one button per map, starting a game on it
+            for map in map_assets.maps.iter() {
+                let Some(details) = assets_maps.get(map) else {
+                    continue;
+                };
+                let play = ui.add(egui::Button::new(
+                    egui::RichText::new(&details.name).size(32.0),
+                ));
+                if play.clicked() {
+                    commands.insert_resource(SelectedMap(map.clone()));
+                    next_state.set(GameState::Game);
+                }
             }
 
+            ui.add_space(10.0);
+
+            ui.add(egui::Label::new(egui::RichText::new("Settings").size(24.0)));
+            ui.checkbox(&mut settings.show_health_bars, "Enemy health bars");
+            ui.checkbox(&mut settings.show_damage_numbers, "Damage numbers");
+
+            ui.add_space(10.0);
+
+            let quit = ui.add(egui::Button::new(egui::RichText::new("Quit").size(24.0)));
+
             if quit.clicked() {
                 writer.send(AppExit::Success);
             }
@@ -0,0 +1,58 @@
+use crate::game::WaveCounter;
+
+use super::GameState;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+pub struct ResultsPlugin;
+
+impl Plugin for ResultsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.add_systems(
+            Update,
+            ui.run_if(in_state(GameState::GameOver).or(in_state(GameState::Victory))),
+        );
+    }
+}
+
+fn ui(
+    mut contexts: EguiContexts,
+    state: Res<State<GameState>>,
+    wave_counter: Res<WaveCounter>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let ctx = contexts.ctx_mut();
+
+    let title = match state.get() {
+        GameState::Victory => "Victory!",
+        _ => "Game Over",
+    };
+
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.with_layout(egui::Layout::top_down(egui::Align::Center), |ui| {
+            ui.style_mut().spacing.item_spacing = egui::vec2(0.0, 10.0);
+
+            ui.add(egui::Label::new(egui::RichText::new(title).size(64.0)));
+            ui.add(egui::Label::new(
+                egui::RichText::new(format!("Waves cleared: {}", wave_counter.current)).size(24.0),
+            ));
+
+            ui.add_space(10.0);
+
+            let restart = ui.add(egui::Button::new(egui::RichText::new("Restart").size(32.0)));
+            let menu = ui.add(egui::Button::new(egui::RichText::new("Menu").size(24.0)));
+
+            if restart.clicked() {
+                next_state.set(GameState::Game);
+            }
+
+            if menu.clicked() {
+                next_state.set(GameState::Menu);
+            }
+        })
+    });
+}
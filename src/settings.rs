@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Settings>();
+    }
+}
+
+/// Player preferences, changed from the menu.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct Settings {
+    /// Bars above enemies showing how much health they have left
+    pub show_health_bars: bool,
+    /// Numbers floating up from enemies as projectiles hit them
+    pub show_damage_numbers: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            show_health_bars: true,
+            show_damage_numbers: true,
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
 
-use crate::game::{AllAssets, AssetCollections, GltfAssets};
+use crate::game::{AllAssets, AssetCollections, MapAssets};
 
 use super::{despawn_screen, GameState, GAME_NAME};
 
@@ -12,7 +12,7 @@ impl Plugin for SplashPlugin {
         app.add_loading_state(
             LoadingState::new(GameState::Splash)
                 .continue_to_state(GameState::Menu)
-                .load_collection::<GltfAssets>()
+                .load_collection::<MapAssets>()
                 .load_collection::<AllAssets>()
                 .register_dynamic_asset_collection::<AssetCollections>()
                 .with_dynamic_assets_file::<AssetCollections>("game.ron"),